@@ -2,50 +2,26 @@
 extern crate lazy_static;
 extern crate roblox_steam_launcher_shared;
 extern crate steam_vdf;
-extern crate winreg;
 extern crate regex;
 
 use roblox_steam_launcher_shared::*;
+use roblox_steam_launcher_shared::capabilities::Capabilities;
+use roblox_steam_launcher_shared::error::describe;
+use roblox_steam_launcher_shared::gameid::ShortcutId;
+use roblox_steam_launcher_shared::logging::Level;
+use roblox_steam_launcher_shared::platform::current_platform;
 
 use std::process::Command;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::io;
 use std::io::{Read, Write};
 use std::fs::OpenOptions;
 use std::ffi::OsString;
 
-use winreg::RegKey;
-use winreg::enums::*;
-
 use regex::Regex;
 
-fn convert_to_hex_string(input: String) -> String {
-    let mut out = String::new();
-    for c in input.as_bytes() {
-        out += &format!("{:X}", c);
-    }
-    out
-}
-
-#[derive(Debug)]
-enum GameIdError {
-    CommandError(String),
-    Utf8Error(String)
-}
-
-fn get_gameid_raw(target: String, name: String) -> Result<String, GameIdError> {
-    let hex_input = convert_to_hex_string(target + &name);
-    match Command::new("roblox_steam_launcher_gameid.exe").arg(hex_input).output() {
-        Ok(out) => match String::from_utf8(out.stdout) {
-            Ok(s) => Ok(String::from(s.trim())),
-            Err(err) => Err(GameIdError::Utf8Error(format!("{:?}", err))),
-        },
-        Err(err) => Err(GameIdError::CommandError(format!("{:?}", err))),
-    }
-}
-
-fn get_gameid(target: &PathBuf, name: String) -> Result<String, GameIdError> {
-    get_gameid_raw(String::from("\"") + &target.as_os_str().to_string_lossy() + "\"", name)
+fn get_gameid(target: &PathBuf, name: &str) -> String {
+    ShortcutId::new(target, name).rungameid().to_string()
 }
 
 struct SteamUser {
@@ -204,27 +180,21 @@ fn add_roblox_launcher(shortcuts_list: &mut steam_vdf::ValveData, roblox_launche
 }
 
 fn get_steam_directory() -> PathBuf {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let steam_key = hkcu.open_subkey_with_flags("Software\\Valve\\Steam", KEY_READ).expect("Cannot open steam registry key!");
-    let steam_location: String = steam_key.get_value("SteamPath").expect("Cannot read SteamPath key!");
-    return Path::new(&steam_location).to_path_buf();
+    current_platform().steam_directory().expect("Cannot find Steam directory!")
 }
 
 /// Returns (VersionsDirectory, CurrentPlayerLauncherPath)
 fn get_roblox_directories() -> (PathBuf, PathBuf) {
-    let hkcu = RegKey::predef(HKEY_CURRENT_USER);
-    let roblox_key = hkcu.open_subkey_with_flags("Software\\RobloxReg", KEY_READ).expect("Cannot open roblox registry key!");
-    let roblox_location: String = roblox_key.get_value("").expect("Cannot read (Default) key!");
-    let current_path = Path::new(&roblox_location).to_path_buf();
-    let mut base_path = current_path.clone();
-    base_path.pop();
-    base_path.pop();
-    return (base_path, current_path);
+    let directories = current_platform().roblox_directories().expect("Cannot find Roblox directory!");
+    (directories.versions, directories.current_launcher)
 }
 
 fn main() {
     let (roblox_versions_path, roblox_current_path) = get_roblox_directories();
     let steam_path = get_steam_directory();
+    // Most failures here are panics, so keep them in the log the launcher writes too.
+    logging::init("installer", &roblox_versions_path, Level::Info);
+    logging::log_panics();
 
     println!("Roblox Steam Launcher installer and uninstaller");
 
@@ -233,6 +203,12 @@ fn main() {
     println!("Roblox directory: {}", roblox_versions_path.as_os_str().to_string_lossy());
     println!("Steam directory: {}", steam_path.as_os_str().to_string_lossy());
 
+    match journal::recover(&roblox_versions_path) {
+        Ok(Some(recovery)) => println!("{}", recovery),
+        Ok(None) => (),
+        Err(err) => println!("Could not recover from an interrupted install: {}", describe(&err)),
+    }
+
     println!();
     println!("What would you like to do?\n[0] Install\n[1] Uninstall");
 
@@ -262,18 +238,18 @@ fn main() {
 
     match action_choice_num {
         0 => install(roblox_versions_path, roblox_current_path, steam_path),
-        1 => uninstall(roblox_versions_path, roblox_current_path, steam_path),
+        1 => uninstall(roblox_versions_path, steam_path),
         _ => unreachable!(),
     }
 }
 
-fn uninstall(roblox_versions_path: PathBuf, roblox_current_path: PathBuf, steam_path: PathBuf) {
+fn uninstall(roblox_versions_path: PathBuf, steam_path: PathBuf) {
     let mut config_path = roblox_versions_path.clone();
     config_path.push(get_config_file_name());
 
     let mut config = match Config::from_path(&config_path) {
         Ok(config) => config,
-        Err(err) => panic!("{}", err),
+        Err(err) => panic!("{}", describe(&err)),
     };
 
     let mut master_launcher_path = roblox_versions_path.clone();
@@ -350,35 +326,51 @@ fn uninstall(roblox_versions_path: PathBuf, roblox_current_path: PathBuf, steam_
         }
     }
 
-    println!("Files deleted.");
-
-    println!("Reverting changes to current Roblox player version...");
+    // These only exist while or after the launcher has run, so they may be missing.
+    let handoff_path = roblox_versions_path.join(ipc::get_handoff_file_name());
+    let _ = std::fs::remove_file(get_lock_path(&config_path));
+    let _ = std::fs::remove_file(get_lock_path(&handoff_path));
+    let _ = std::fs::remove_file(&handoff_path);
+    let _ = std::fs::remove_file(roblox_versions_path.join(fallback::get_fallback_file_name()));
+    let _ = std::fs::remove_file(roblox_versions_path.join(steam_launch::get_bounce_file_name()));
+    let _ = std::fs::remove_file(roblox_versions_path.join(get_update_cache_file_name()));
+    for generation in 0..=logging::LOG_FILE_GENERATIONS {
+        let _ = std::fs::remove_file(logging::get_log_path(&roblox_versions_path, generation));
+    }
+    let _ = std::fs::remove_file(get_lock_path(&logging::get_log_path(&roblox_versions_path, 0)));
 
-    let normal_exe_path = roblox_current_path;
-    let mut original_exe_path = normal_exe_path.clone();
-    original_exe_path.pop();
-    original_exe_path.push("RobloxPlayerLauncher_original.exe");
+    println!("Files deleted.");
 
-    if original_exe_path.exists() {
+    println!("Reverting changes to Roblox player versions...");
 
-        if let Err(err) = std::fs::remove_file(&normal_exe_path) {
-            println!("Error deleting file: {}", err);
-            println!("Press enter to exit.");
-            std::io::stdin().read_line(&mut String::new()).expect("Failed to read line");
-            return;
-        }
-
-        if let Err(err) = std::fs::rename(&original_exe_path, normal_exe_path) {
-            println!("Error renaming file: {}", err);
+    // Older versions may still have the launcher applied if the master never got to repair them.
+    let versions = match versions::discover_versions(&roblox_versions_path, versions::VersionOrder::Created) {
+        Ok(versions) => versions,
+        Err(err) => {
+            println!("Error finding Roblox versions: {}", describe(&err));
             println!("Press enter to exit.");
             std::io::stdin().read_line(&mut String::new()).expect("Failed to read line");
             return;
+        },
+    };
+    let mut failures = 0;
+    for directory in versions.iter().filter(|directory| directory.is_player()) {
+        match install::unapply(&directory.path) {
+            Ok(install::UnapplyOutcome::Unapplied) => println!("{}: changes reverted.", directory.version),
+            Ok(install::UnapplyOutcome::AlreadyPristine) => println!("{}: changes appear to already be reverted.", directory.version),
+            Err(err) => {
+                println!("{}: error reverting changes: {}", directory.version, describe(&err));
+                failures += 1;
+            },
         }
-
-        println!("Changes reverted.");
-    } else {
-        println!("Changes appear to already be reverted.");
     }
+    if failures > 0 {
+        println!("Could not revert changes to {} of the Roblox versions above.", failures);
+        println!("Press enter to exit.");
+        std::io::stdin().read_line(&mut String::new()).expect("Failed to read line");
+        return;
+    }
+    let _ = std::fs::remove_file(get_lock_path(&roblox_versions_path.join(journal::get_journal_file_name())));
 
     println!();
     println!("Done. Roblox Steam Launcher should be uninstalled.");
@@ -387,6 +379,23 @@ fn uninstall(roblox_versions_path: PathBuf, roblox_current_path: PathBuf, steam_
     std::io::stdin().read_line(&mut String::new()).expect("Failed to read line");
 }
 
+/// Checks that the master and substitute next to the installer were built to work together, and
+/// with this installer, before they are copied.
+fn check_launcher_versions(program_dir: &PathBuf) -> Result<(), String> {
+    let substitute_path = program_dir.join(install::SUBSTITUTE_FILE_NAME);
+    // Only a substitute that answers `--capabilities` may be run to ask; older ones would launch.
+    match install::substitute_version(&substitute_path) {
+        Ok(Some(version)) if version == install::SUBSTITUTE_VERSION => (),
+        Ok(Some(version)) => return Err(format!("The substitute is version {}, but this installer expects version {}.", version, install::SUBSTITUTE_VERSION)),
+        Ok(None) => return Err(format!("{} is missing or is not the Roblox Steam Launcher substitute.", substitute_path.display())),
+        Err(err) => return Err(format!("Cannot read {}: {}", substitute_path.display(), err)),
+    }
+    let master_path = program_dir.join("roblox_steam_launcher_master.exe");
+    let master = Capabilities::query(&master_path).map_err(|err| format!("{}: {}", master_path.display(), describe(&err)))?;
+    let substitute = Capabilities::query(&substitute_path).map_err(|err| format!("{}: {}", substitute_path.display(), describe(&err)))?;
+    master.check_compatible(&substitute, CONFIG_SCHEMA_VERSION).map_err(|err| err.to_string())
+}
+
 fn install(roblox_versions_path: PathBuf, roblox_current_path: PathBuf, steam_path: PathBuf) {
     let mut config_path = roblox_versions_path.clone();
     config_path.push(get_config_file_name());
@@ -454,6 +463,13 @@ fn install(roblox_versions_path: PathBuf, roblox_current_path: PathBuf, steam_pa
     } else {
 
 
+        if let Err(err) = check_launcher_versions(&program_dir) {
+            println!("Cannot install these files: {}", err);
+            println!("Press enter to exit.");
+            std::io::stdin().read_line(&mut String::new()).expect("Failed to read line");
+            return;
+        }
+
         let copy_files = ["roblox_steam_launcher_master.exe", "roblox_steam_launcher_substitute.exe"];
 
         println!("Copying files to Roblox\\Versions directory...");
@@ -480,11 +496,21 @@ fn install(roblox_versions_path: PathBuf, roblox_current_path: PathBuf, steam_pa
         println!("Adding launcher to Steam as a non-Steam game...");
         let mut shortcuts = read_shortcuts(&users[steam_user_choice_num].userdata_dir).unwrap().unwrap();
         let (abs_launcher_path, launcher_steam_name) = add_roblox_launcher(&mut shortcuts, &master_launcher_path, String::from("Roblox"));
-        steam_gameid = get_gameid(&abs_launcher_path, launcher_steam_name).unwrap();
+        steam_gameid = get_gameid(&abs_launcher_path, &launcher_steam_name);
         write_shortcuts(&users[steam_user_choice_num].userdata_dir, &shortcuts).unwrap();
         println!("Added launcher to Steam. Game id: {}", steam_gameid);
     }
 
+    let config_lock = match FileLock::acquire(&config_path) {
+        Ok(lock) => lock,
+        Err(err) => {
+            println!("Error locking config file: {}", err);
+            println!("Press enter to exit.");
+            std::io::stdin().read_line(&mut String::new()).expect("Failed to read line");
+            return;
+        }
+    };
+
     if !config_path.exists() {
         println!("Generating configuration file...");
 
@@ -494,7 +520,7 @@ fn install(roblox_versions_path: PathBuf, roblox_current_path: PathBuf, steam_pa
         match config.write_to_path(&config_path) {
             Ok(_) => (),
             Err(err) => {
-                println!("Error creating config file!");
+                println!("Error creating config file: {}", describe(&err));
                 println!("Press enter to exit.");
                 std::io::stdin().read_line(&mut String::new()).expect("Failed to read line");
                 return;
@@ -504,6 +530,8 @@ fn install(roblox_versions_path: PathBuf, roblox_current_path: PathBuf, steam_pa
         println!("Generated and saved config file.");
     }
 
+    drop(config_lock);
+
     println!("Running roblox_steam_launcher_master.");
 
     match Command::new(master_launcher_path).output() {
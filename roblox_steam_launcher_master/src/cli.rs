@@ -0,0 +1,150 @@
+//! The master's command line.
+
+/// The command did what was asked.
+pub const EXIT_SUCCESS: i32 = 0;
+/// Something went wrong that has no code of its own.
+pub const EXIT_FAILURE: i32 = 1;
+/// The command line could not be understood.
+pub const EXIT_USAGE: i32 = 2;
+/// The config could not be read or written, or `config set` was given an invalid value.
+pub const EXIT_CONFIG: i32 = 3;
+/// A Roblox version directory could not be applied, unapplied or repaired.
+pub const EXIT_INSTALL: i32 = 4;
+/// `check-update` found that Roblox needs an update.
+pub const EXIT_OUTDATED: i32 = 5;
+/// `check-update` could not find out whether Roblox needs an update.
+pub const EXIT_UPDATE_UNKNOWN: i32 = 6;
+
+pub const USAGE: &str = "\
+Usage: roblox_steam_launcher_master [COMMAND] [--json] [--no-pause]
+
+Commands:
+  launch               Launch the request the substitute handed off (the default)
+  apply [VERSION]      Apply the launcher to a version directory, the newest by default
+  unapply [VERSION]    Put Roblox's launcher back in a version directory, the newest by default
+  repair               Apply the launcher to the newest version and revert every other version
+  status               Show whether the launcher is applied to each version
+  check-update         Ask Roblox whether the newest version is the latest
+  config get [KEY]     Show the config, or one value in it, such as update_check.channel
+  config set KEY VALUE Change one value in the config. VALUE is read as JSON, or else as text
+
+Options:
+  --json               Print results as JSON
+  --no-pause           Never wait for enter before exiting
+  --version            Print the version
+  --capabilities       Print what this build supports as JSON
+
+Exit codes:
+  0  Success
+  1  Failure
+  2  Invalid command line
+  3  The config could not be read, written or changed
+  4  A version directory could not be applied, unapplied or repaired
+  5  check-update: Roblox needs an update
+  6  check-update: whether Roblox needs an update is unknown";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Launch,
+    Apply { version: Option<String> },
+    Unapply { version: Option<String> },
+    Repair,
+    Status,
+    CheckUpdate,
+    ConfigGet { key: Option<String> },
+    ConfigSet { key: String, value: String },
+    Help,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Options {
+    pub command: Command,
+    pub json: bool,
+    pub no_pause: bool,
+}
+
+fn at_most_one(command: &str, operands: &[String]) -> Result<Option<String>, String> {
+    match operands {
+        [] => Ok(None),
+        [operand] => Ok(Some(operand.clone())),
+        _ => Err(format!("{} takes at most one argument", command)),
+    }
+}
+
+fn no_operands(command: Command, name: &str, operands: &[String]) -> Result<Command, String> {
+    if operands.is_empty() {
+        Ok(command)
+    } else {
+        Err(format!("{} takes no arguments", name))
+    }
+}
+
+/// Parses the arguments after the program name.
+pub fn parse(arguments: &[String]) -> Result<Options, String> {
+    let mut json = false;
+    let mut no_pause = false;
+    let mut help = false;
+    let mut positional = vec![];
+    for argument in arguments {
+        match argument.as_str() {
+            "--json" => json = true,
+            "--no-pause" => no_pause = true,
+            "--help" | "-h" => help = true,
+            flag if flag.starts_with("--") => return Err(format!("unknown option {}", flag)),
+            _ => positional.push(argument.clone()),
+        }
+    }
+    let command = if help {
+        Command::Help
+    } else {
+        match positional.split_first() {
+            None => Command::Launch,
+            Some((name, operands)) => match name.as_str() {
+                "launch" => no_operands(Command::Launch, name, operands)?,
+                "apply" => Command::Apply { version: at_most_one(name, operands)? },
+                "unapply" => Command::Unapply { version: at_most_one(name, operands)? },
+                "repair" => no_operands(Command::Repair, name, operands)?,
+                "status" => no_operands(Command::Status, name, operands)?,
+                "check-update" => no_operands(Command::CheckUpdate, name, operands)?,
+                "help" => Command::Help,
+                "config" => match operands {
+                    [action, rest @ ..] if action == "get" => Command::ConfigGet { key: at_most_one("config get", rest)? },
+                    [action, key, value] if action == "set" => Command::ConfigSet { key: key.clone(), value: value.clone() },
+                    [action, ..] if action == "set" => return Err(String::from("config set takes a key and a value")),
+                    _ => return Err(String::from("config takes get or set")),
+                },
+                other => return Err(format!("unknown command {}", other)),
+            },
+        }
+    };
+    Ok(Options { command, json, no_pause })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(arguments: &[&str]) -> Result<Options, String> {
+        parse(&arguments.iter().map(|argument| String::from(*argument)).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(parse_str(&[]).unwrap(), Options { command: Command::Launch, json: false, no_pause: false });
+        assert_eq!(parse_str(&["apply", "--json", "version-abc"]).unwrap(), Options { command: Command::Apply { version: Some(String::from("version-abc")) }, json: true, no_pause: false });
+        assert_eq!(parse_str(&["--no-pause", "repair"]).unwrap().command, Command::Repair);
+        assert_eq!(parse_str(&["config", "get"]).unwrap().command, Command::ConfigGet { key: None });
+        assert_eq!(parse_str(&["config", "set", "debug", "true"]).unwrap().command, Command::ConfigSet { key: String::from("debug"), value: String::from("true") });
+        assert_eq!(parse_str(&["status", "--help"]).unwrap().command, Command::Help);
+    }
+
+    #[test]
+    fn rejects_bad_command_lines() {
+        assert!(parse_str(&["launch", "extra"]).is_err());
+        assert!(parse_str(&["apply", "a", "b"]).is_err());
+        assert!(parse_str(&["config", "set", "debug"]).is_err());
+        assert!(parse_str(&["config"]).is_err());
+        assert!(parse_str(&["frobnicate"]).is_err());
+        assert!(parse_str(&["--verbose"]).is_err());
+    }
+}
@@ -0,0 +1,275 @@
+//! The commands besides `launch`, for inspecting and fixing an install from scripts.
+
+use cli::{EXIT_INSTALL, EXIT_OUTDATED, EXIT_SUCCESS, EXIT_UPDATE_UNKNOWN};
+use update_check::{check_for_update_now, UpdateStatus};
+use {Paths, UIErr};
+
+use roblox_steam_launcher_shared::Config;
+use roblox_steam_launcher_shared::error::describe;
+use roblox_steam_launcher_shared::install::{apply, repair, substitute_version, unapply, ExecutableKind, InstallState, RepairAction};
+use roblox_steam_launcher_shared::journal::recover;
+use roblox_steam_launcher_shared::versions::{discover_versions, find_newest_player_version, VersionOrder, LAUNCHER_FILE_NAME};
+
+use serde_json;
+use serde_json::Value;
+
+use std::path::{Component, Path, PathBuf};
+
+/// Prints `json` if JSON output was asked for, or else `text`.
+fn print_result(json_output: bool, json: Value, text: &str) {
+    if json_output {
+        println!("{}", json);
+    } else {
+        println!("{}", text);
+    }
+}
+
+/// Prints something that happened along the way. With JSON output this goes to stderr, so stdout
+/// stays parseable.
+fn print_notice(json_output: bool, notice: &str) {
+    if json_output {
+        eprintln!("{}", notice);
+    } else {
+        println!("{}", notice);
+    }
+}
+
+fn version_name(path: &Path) -> String {
+    path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().into_owned()
+}
+
+/// Finishes or undoes an install that was interrupted, before anything else is changed.
+pub fn recover_interrupted(paths: &Paths, json_output: bool) -> Result<(), UIErr> {
+    match recover(&paths.program_directory) {
+        Ok(Some(recovery)) => print_notice(json_output, &recovery.to_string()),
+        Ok(None) => (),
+        Err(err) => return Err(UIErr::Apply("Could not recover from an interrupted install", err)),
+    }
+    Ok(())
+}
+
+/// The version directory named `version`, or the newest player version. A name has to be one of
+/// the directories under the program directory, so it can't point anywhere else.
+fn find_version(paths: &Paths, version: &Option<String>) -> Result<PathBuf, UIErr> {
+    match version {
+        Some(name) => {
+            let mut components = Path::new(name).components();
+            match (components.next(), components.next()) {
+                (Some(Component::Normal(_)), None) => (),
+                _ => return Err(UIErr::String("Not a Roblox version directory name", name.clone())),
+            }
+            let versions = match discover_versions(&paths.program_directory, VersionOrder::Created) {
+                Ok(versions) => versions,
+                Err(err) => return Err(UIErr::String("Could not find the Roblox version directories", describe(&err))),
+            };
+            match versions.into_iter().find(|directory| directory.version == *name) {
+                Some(directory) => Ok(directory.path),
+                None => Err(UIErr::String("No such Roblox version directory", name.clone())),
+            }
+        },
+        None => match find_newest_player_version(&paths.program_directory, VersionOrder::Created) {
+            Ok(Some(version)) => Ok(version.path),
+            Ok(None) => Err(UIErr::Simple("Could not find a Roblox player version directory")),
+            Err(err) => Err(UIErr::String("Error getting Roblox newest directory", describe(&err))),
+        },
+    }
+}
+
+pub fn apply_version(paths: &Paths, version: &Option<String>, json_output: bool) -> Result<i32, UIErr> {
+    recover_interrupted(paths, json_output)?;
+    let path = find_version(paths, version)?;
+    let outcome = match apply(&path, &paths.substitute_path) {
+        Ok(outcome) => outcome,
+        Err(err) => return Err(UIErr::Apply("Error applying launcher", err)),
+    };
+    let json = json!({ "version": version_name(&path), "path": path, "outcome": outcome });
+    print_result(json_output, json, &format!("{}: {}", version_name(&path), RepairAction::Applied(outcome)));
+    Ok(EXIT_SUCCESS)
+}
+
+pub fn unapply_version(paths: &Paths, version: &Option<String>, json_output: bool) -> Result<i32, UIErr> {
+    recover_interrupted(paths, json_output)?;
+    let path = find_version(paths, version)?;
+    let outcome = match unapply(&path) {
+        Ok(outcome) => outcome,
+        Err(err) => return Err(UIErr::Apply("Error unapplying launcher", err)),
+    };
+    let json = json!({ "version": version_name(&path), "path": path, "outcome": outcome });
+    print_result(json_output, json, &format!("{}: {}", version_name(&path), RepairAction::Unapplied(outcome)));
+    Ok(EXIT_SUCCESS)
+}
+
+/// Applies the launcher to the current version, reverts every other version, and reports on
+/// each of them.
+pub fn repair_versions(paths: &Paths, json_output: bool) -> Result<i32, UIErr> {
+    recover_interrupted(paths, json_output)?;
+    let reports = match repair(&paths.program_directory, &paths.substitute_path) {
+        Ok(reports) => reports,
+        Err(err) => return Err(UIErr::String("Could not find the Roblox version directories", describe(&err))),
+    };
+    let (mut changed, mut unchanged, mut failed) = (0, 0, 0);
+    let mut versions = vec![];
+    let mut text = String::new();
+    for report in &reports {
+        text.push_str(&format!("{}\n", report));
+        let mut json = json!({ "version": version_name(&report.path), "path": report.path, "current": report.current, "state": report.state });
+        match report.result {
+            Ok(action) => {
+                if action.changed() {
+                    changed += 1;
+                } else {
+                    unchanged += 1;
+                }
+                json["action"] = Value::String(action.to_string());
+                json["changed"] = Value::Bool(action.changed());
+            },
+            Err(ref err) => {
+                failed += 1;
+                json["error"] = Value::String(describe(err));
+            },
+        }
+        versions.push(json);
+    }
+    text.push_str(&format!("\nChecked {} Roblox versions: {} repaired, {} already correct, {} could not be repaired.", reports.len(), changed, unchanged, failed));
+    let json = json!({ "versions": versions, "repaired": changed, "unchanged": unchanged, "failed": failed });
+    print_result(json_output, json, &text);
+    Ok(if failed == 0 { EXIT_SUCCESS } else { EXIT_INSTALL })
+}
+
+/// Reports the install state of every player version, and the substitute's version.
+pub fn status(paths: &Paths, json_output: bool) -> Result<i32, UIErr> {
+    let directories = match discover_versions(&paths.program_directory, VersionOrder::Created) {
+        Ok(directories) => directories,
+        Err(err) => return Err(UIErr::String("Could not find the Roblox version directories", describe(&err))),
+    };
+    let substitute = substitute_version(&paths.substitute_path).ok().and_then(|version| version);
+    let mut text = match substitute {
+        Some(version) => format!("Substitute: version {}\n", version),
+        None => String::from("Substitute: missing\n"),
+    };
+    let mut versions = vec![];
+    for (index, directory) in directories.iter().filter(|directory| directory.is_player()).enumerate() {
+        let launcher_path = directory.path.join(LAUNCHER_FILE_NAME);
+        let applied_version = match ExecutableKind::of(&launcher_path) {
+            Ok(ExecutableKind::Substitute) => substitute_version(&launcher_path).ok().and_then(|version| version),
+            _ => None,
+        };
+        let state = InstallState::of(&directory.path);
+        text.push_str(&format!("{}{}: ", directory.version, if index == 0 { " (current)" } else { "" }));
+        match state {
+            Ok(ref state) => text.push_str(&state.to_string()),
+            Err(ref err) => text.push_str(&format!("unknown ({})", describe(err))),
+        }
+        if let Some(version) = applied_version {
+            text.push_str(&format!(", substitute version {}", version));
+        }
+        text.push('\n');
+        let state = match state {
+            Ok(state) => json!(state),
+            Err(err) => json!({ "unknown": describe(&err) }),
+        };
+        versions.push(json!({ "version": directory.version, "path": directory.path, "current": index == 0, "state": state, "substitute_version": applied_version }));
+    }
+    let json = json!({ "substitute_version": substitute, "versions": versions });
+    print_result(json_output, json, text.trim_end());
+    Ok(EXIT_SUCCESS)
+}
+
+pub fn check_update(paths: &Paths, config: &Config, json_output: bool) -> Result<i32, UIErr> {
+    let path = find_version(paths, &None)?;
+    let current = version_name(&path);
+    let status = check_for_update_now(&config.update_check, &current, &paths.program_directory);
+    let (text, json, code) = match status {
+        UpdateStatus::UpToDate => (format!("{} is the latest version.", current), json!({ "status": "up_to_date" }), EXIT_SUCCESS),
+        UpdateStatus::Outdated { latest } => (format!("{} is outdated. The latest version is {}.", current, latest), json!({ "status": "outdated", "latest": latest }), EXIT_OUTDATED),
+        UpdateStatus::Unknown { reason } => (format!("Could not check whether {} is the latest version: {}", current, reason), json!({ "status": "unknown", "reason": reason }), EXIT_UPDATE_UNKNOWN),
+    };
+    let mut json = json;
+    json["current"] = Value::String(current);
+    print_result(json_output, json, &text);
+    Ok(code)
+}
+
+/// Turns a dotted key such as `update_check.channel` into a JSON pointer.
+fn config_pointer(key: &str) -> String {
+    format!("/{}", key.replace('.', "/"))
+}
+
+pub fn config_get(paths: &Paths, key: &Option<String>, json_output: bool) -> Result<i32, UIErr> {
+    let config = match Config::from_path(&paths.config_path) {
+        Ok(config) => config,
+        Err(err) => return Err(UIErr::ConfigRead("Could not read config file", err)),
+    };
+    let document = match serde_json::to_value(&config) {
+        Ok(document) => document,
+        Err(err) => return Err(UIErr::Config("Could not read config file", err.to_string())),
+    };
+    let value = match key {
+        Some(key) => match document.pointer(&config_pointer(key)) {
+            Some(value) => value.clone(),
+            None => return Err(UIErr::Config("No such config value", key.clone())),
+        },
+        None => document,
+    };
+    let text = match value {
+        Value::String(ref text) => text.clone(),
+        ref other => serde_json::to_string_pretty(other).unwrap_or_default(),
+    };
+    print_result(json_output, value, &text);
+    Ok(EXIT_SUCCESS)
+}
+
+/// Sets the config value at `key`. `value` is read as JSON if it is JSON, or else as a string.
+/// Only values that are already in the config can be set, so a typo can't go unnoticed.
+pub fn config_set(paths: &Paths, key: &str, value: &str, json_output: bool) -> Result<i32, UIErr> {
+    if key == "schema_version" {
+        return Err(UIErr::Config("This config value can't be set", String::from(key)));
+    }
+    let (config, _lock) = match Config::from_path_locked(&paths.config_path) {
+        Ok(locked) => locked,
+        Err(err) => return Err(UIErr::ConfigRead("Could not read config file", err)),
+    };
+    let mut document = match serde_json::to_value(&config) {
+        Ok(document) => document,
+        Err(err) => return Err(UIErr::Config("Could not read config file", err.to_string())),
+    };
+    let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(String::from(value)));
+    match document.pointer_mut(&config_pointer(key)) {
+        Some(slot) => *slot = value.clone(),
+        None => return Err(UIErr::Config("No such config value", String::from(key))),
+    }
+    let config: Config = match serde_json::from_value(document) {
+        Ok(config) => config,
+        Err(err) => return Err(UIErr::Config("Invalid config value", format!("{}: {}", key, err))),
+    };
+    if let Err(err) = config.write_to_path(&paths.config_path) {
+        return Err(UIErr::ConfigWrite("Could not write config file", err));
+    }
+    print_result(json_output, json!({ "key": key, "value": value }), &format!("Set {}", key));
+    Ok(EXIT_SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn finds_only_discovered_versions() {
+        let dir = std::env::temp_dir().join(format!("roblox_steam_launcher_commands_test_{}", std::process::id()));
+        let program_directory = dir.join("Versions");
+        fs::create_dir_all(program_directory.join("version-abc")).unwrap();
+        fs::create_dir_all(dir.join("elsewhere")).unwrap();
+        let paths = Paths { program_directory: program_directory.clone(), config_path: dir.join("config.json"), substitute_path: dir.join("substitute.exe") };
+        let find = |name: &str| find_version(&paths, &Some(String::from(name)));
+
+        assert_eq!(find("version-abc").unwrap(), program_directory.join("version-abc"));
+        for name in &["version-def", "../elsewhere", "version-abc/..", ".", "", dir.join("elsewhere").to_str().unwrap()] {
+            match find(name) {
+                Err(UIErr::String(..)) => (),
+                other => panic!("expected {:?} to be refused, got {:?}", name, other),
+            }
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
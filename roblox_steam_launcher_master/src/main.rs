@@ -1,252 +1,443 @@
+#[macro_use]
 extern crate roblox_steam_launcher_shared;
 extern crate hyper;
+extern crate native_tls;
 extern crate notify;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+#[macro_use]
+extern crate serde_json;
+
+mod cli;
+mod commands;
+mod update_check;
+mod watcher;
 
 use roblox_steam_launcher_shared::*;
-use std::path::PathBuf;
-use hyper::client::Client;
-use std::io::Read;
-use notify::{RecommendedWatcher, Watcher, RecursiveMode};
-use std::sync::mpsc::{channel, RecvTimeoutError};
-use std::ffi::OsStr;
-use std::time::{Duration, Instant};
+use roblox_steam_launcher_shared::capabilities::print_if_requested;
+use roblox_steam_launcher_shared::error::describe;
+use roblox_steam_launcher_shared::fallback::FallbackRecord;
+use roblox_steam_launcher_shared::install::{apply, unapply, InstallError, InstallState, SUBSTITUTE_FILE_NAME};
+use roblox_steam_launcher_shared::ipc::*;
+use roblox_steam_launcher_shared::launch_uri::check_arguments;
+use roblox_steam_launcher_shared::logging;
+use roblox_steam_launcher_shared::logging::Level;
+use roblox_steam_launcher_shared::platform::{current_platform, Platform, ProcessInfo};
+use roblox_steam_launcher_shared::steam_launch::{begin_bounce, end_bounce, started_by_steam};
+use roblox_steam_launcher_shared::supervise::supervise;
+use roblox_steam_launcher_shared::versions::{find_newest_player_version, VersionOrder};
+use cli::{Command, Options};
+use update_check::{check_for_update_cached, check_for_update_in_background, UpdateStatus};
+use watcher::{watch_for_new_exe, WatchOutcome};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::time::Duration;
 
-#[derive(Debug)]
-enum ApplyError {
-    NoExecutable,
-    NoProgramDir,
-    CannotRename(std::io::Error),
-    CannotCopy(std::io::Error),
-    CannotRemove(std::io::Error),
-}
+/// How long a background update check may run on after the launcher has been applied.
+const BACKGROUND_UPDATE_CHECK_GRACE: Duration = Duration::from_millis(250);
 
 #[derive(Debug)]
-enum ApplyResult {
-    Applied,
-    AlreadyApplied,
-    Error(ApplyError)
-}
-
-fn check_if_newest_version(current_version: String) -> hyper::error::Result<bool> {
-    let client = Client::new();
-    let mut res = client.get("http://setup.roblox.com/version").send()?;
-    let mut msg = String::new();
-    res.read_to_string(&mut msg)?;
-    Ok(msg == current_version)
+enum UIErr {
+    Simple(&'static str),
+    String(&'static str, String),
+    ConfigRead(&'static str, ConfigReadError),
+    ConfigWrite(&'static str, ConfigWriteError),
+    /// A config value that couldn't be found or changed.
+    Config(&'static str, String),
+    Handoff(&'static str, HandoffError),
+    Apply(&'static str, InstallError),
 }
 
-fn apply_launcher(version_path: &PathBuf) -> ApplyResult {
-    let mut new_application_path = version_path.clone();
-    new_application_path.push("RobloxPlayerLauncher_original.exe");
-    if new_application_path.is_file() {
-        return ApplyResult::AlreadyApplied;  // We've already applied the launcher here!
-    }
-    let mut new_substitute_path = version_path.clone();
-    new_substitute_path.push("RobloxPlayerLauncher.exe");
-    if !new_substitute_path.is_file() {
-        return ApplyResult::Error(ApplyError::NoExecutable);  // We can't apply to a non-existent executable.
-    }
-    let mut substitute_base_path = match get_program_directory(&mut std::env::args()) {
-        Some(path) => path,
-        None => return ApplyResult::Error(ApplyError::NoProgramDir),
-    };
-    substitute_base_path.push("roblox_steam_launcher_substitute.exe");
-    if let Err(err) = std::fs::rename(&new_substitute_path, new_application_path) {
-        return ApplyResult::Error(ApplyError::CannotRename(err));
-    }
-    if let Err(err) = std::fs::copy(&substitute_base_path, new_substitute_path) {
-        return ApplyResult::Error(ApplyError::CannotCopy(err));
+impl UIErr {
+    fn exit_code(&self) -> i32 {
+        match self {
+            UIErr::ConfigRead(..) | UIErr::ConfigWrite(..) | UIErr::Config(..) => cli::EXIT_CONFIG,
+            UIErr::Apply(..) => cli::EXIT_INSTALL,
+            UIErr::Simple(..) | UIErr::String(..) | UIErr::Handoff(..) => cli::EXIT_FAILURE,
+        }
     }
-    return ApplyResult::Applied;
-}
 
-fn unapply_launcher(version_path: &PathBuf) -> ApplyResult {
-    let mut new_application_path = version_path.clone();
-    new_application_path.push("RobloxPlayerLauncher_original.exe");
-    if !new_application_path.is_file() {
-        return ApplyResult::AlreadyApplied;
-    }
-    let mut new_substitute_path = version_path.clone();
-    new_substitute_path.push("RobloxPlayerLauncher.exe");
-    if new_substitute_path.exists() {
-        if let Err(err) = std::fs::remove_file(&new_substitute_path) {
-            return ApplyResult::Error(ApplyError::CannotRemove(err));
+    fn reason(&self) -> &'static str {
+        match self {
+            UIErr::Simple(reason)
+            | UIErr::String(reason, _)
+            | UIErr::ConfigRead(reason, _)
+            | UIErr::ConfigWrite(reason, _)
+            | UIErr::Config(reason, _)
+            | UIErr::Handoff(reason, _)
+            | UIErr::Apply(reason, _) => reason,
         }
     }
-    if let Err(err) = std::fs::rename(&new_application_path, new_substitute_path) {
-        return ApplyResult::Error(ApplyError::CannotRename(err));
+
+    /// What went wrong underneath, with its whole chain of causes.
+    fn detail(&self) -> Option<String> {
+        match self {
+            UIErr::Simple(_) => None,
+            UIErr::String(_, detail) | UIErr::Config(_, detail) => Some(detail.clone()),
+            UIErr::ConfigRead(_, err) => Some(describe(err)),
+            UIErr::ConfigWrite(_, err) => Some(describe(err)),
+            UIErr::Handoff(_, err) => Some(describe(err)),
+            UIErr::Apply(_, err) => Some(describe(err)),
+        }
     }
-    return ApplyResult::Applied;
 }
 
-fn watch_for_new_exe(version_path: &PathBuf, config_debug: bool) {
-    let (tx, rx) = channel();
-    let mut watcher: RecommendedWatcher = Watcher::new(tx, Duration::from_secs(0)).expect("Could not create file watcher");
-    watcher.watch(version_path, RecursiveMode::Recursive).expect("Could not watch directory");
-    if config_debug {
-        println!("Watching for changes...");
-    }
-    let start = Instant::now();
-    let timeout_duration = Duration::from_secs(120);
-    loop {
-        match rx.recv_timeout(Duration::from_secs(10)) {
-            Ok(event) => {
-                if config_debug {
-                    println!("{:?}", event);
-                }
-                if let notify::DebouncedEvent::Create(new_path) = event {
-                    if new_path.file_name() == Some(OsStr::new("RobloxPlayerLauncher.exe")) {
-                        //fix up this new exe
-                        if config_debug {
-                            println!("Applying launcher...");
-                        }
-                        let mut version_path = new_path.clone();
-                        if config_debug {
-                            println!("New launcher at: {:?}", version_path);
-                        }
-                        version_path.pop();
-                        let result = apply_launcher(&version_path);
-                        if config_debug {
-                            println!("Result: {:?}", result);
-                        }
-                        break;
-                    }
-                }
-            },
-            Err(RecvTimeoutError::Timeout) => {
-                if start.elapsed() >= timeout_duration && config_debug {
-                    println!("Timeout reached. Exiting.");
-                }
-            },
-            Err(RecvTimeoutError::Disconnected) => {
-                if config_debug {
-                    println!("watch error: disconnected");
-                }
-                break;
-            },
+impl fmt::Display for UIErr {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.detail() {
+            Some(detail) => write!(formatter, "{}: {}", self.reason(), detail),
+            None => write!(formatter, "{}", self.reason()),
         }
     }
-    if config_debug {
-        println!("Done watching for changes.");
-    }
 }
 
-enum UIErr {
-    Simple(&'static str),
-    String(&'static str, String),
-    ConfigRead(&'static str, ConfigReadError),
-    ConfigWrite(&'static str, ConfigWriteError),
-    Apply(&'static str, ApplyError),
+/// Where the master finds the files it works with.
+pub struct Paths {
+    pub program_directory: PathBuf,
+    pub config_path: PathBuf,
+    pub substitute_path: PathBuf,
+}
+
+fn read_config(paths: &Paths) -> Result<Config, UIErr> {
+    match Config::from_path(&paths.config_path) {
+        Ok(config) => Ok(config),
+        Err(err) => Err(UIErr::ConfigRead("Could not read config file", err)),
+    }
 }
 
-fn errorable_main() -> Result<bool, UIErr> {
+/// Runs the command in `options`. Returns the exit code, and whether to wait for enter first.
+fn errorable_main(options: &Options) -> Result<(i32, bool), UIErr> {
     let program_directory = match get_program_directory(&mut std::env::args()) {
         Some(path) => path,
         None => return Err(UIErr::Simple("Cannot get program directory.")),
     };
-    let mut config_path = program_directory.clone();
-    config_path.push(get_config_file_name());  // down to config file
-    let mut config = match Config::from_path(&config_path) {
-        Ok(config) => config,
-        Err(err) => return Err(UIErr::ConfigRead("Could not read config file", err)),
+    let paths = Paths {
+        config_path: program_directory.join(get_config_file_name()),
+        substitute_path: program_directory.join(SUBSTITUTE_FILE_NAME),
+        program_directory,
     };
-    let config_debug = config.debug;
-    if config_debug {
-        println!("Read config file");
-    }
-    let config_arguments = config.arguments;
-    config.arguments = vec![];
-    match config.write_to_path(&config_path) {
-        Ok(_) => (),
-        Err(err) => return Err(UIErr::ConfigWrite("Could not write config file", err)),
+    logging::init("master", &paths.program_directory, Level::Info);
+    logging::log_panics();
+    let code = match options.command {
+        Command::Launch => return launch(&paths, &*current_platform()),
+        Command::Help => {
+            println!("{}", cli::USAGE);
+            cli::EXIT_SUCCESS
+        },
+        Command::Apply { ref version } => commands::apply_version(&paths, version, options.json)?,
+        Command::Unapply { ref version } => commands::unapply_version(&paths, version, options.json)?,
+        Command::Repair => commands::repair_versions(&paths, options.json)?,
+        Command::Status => commands::status(&paths, options.json)?,
+        Command::CheckUpdate => commands::check_update(&paths, &read_config(&paths)?, options.json)?,
+        Command::ConfigGet { ref key } => commands::config_get(&paths, key, options.json)?,
+        Command::ConfigSet { ref key, ref value } => commands::config_set(&paths, key, value, options.json)?,
     };
-    if config_debug {
-        println!("Cleared arguments and wrote config file");
+    Ok((code, false))
+}
+
+/// Launches the request the substitute handed off, applying the launcher and updating Roblox on
+/// the way, with `platform` to reach Steam and the processes. Returns the exit code, which is the
+/// Roblox client's when supervising, and whether debugging is on.
+fn launch(paths: &Paths, platform: &dyn Platform) -> Result<(i32, bool), UIErr> {
+    let program_directory = paths.program_directory.clone();
+    let substitute_path = paths.substitute_path.clone();
+    let config = read_config(paths)?;
+    let config_debug = config.debug;
+    logging::set_level(Level::from_debug(config_debug));
+    // Warnings were always printed, and everything else only when debugging.
+    logging::set_console_level(Some(if config_debug { Level::Debug } else { Level::Warn }));
+    log_debug!("Read config file");
+    if started_by_steam() {
+        log_info!("Started by Steam");
+        if let Err(err) = end_bounce(&program_directory) {
+            log_warn!("Could not clear the Steam restart record: {}", describe(&err));
+        }
+    } else if config.bounce_through_steam {
+        // Leave the launch request queued for the copy of us that Steam starts.
+        match begin_bounce(&program_directory, Duration::from_secs(config.handoff_timeout_secs)) {
+            Ok(true) => {
+                log_info!("Not started by Steam. Restarting through Steam...");
+                if let Err(err) = launch_steam(platform, &config.steam_launch, &config.steam_gameid) {
+                    return Err(UIErr::String("Could not restart through Steam", describe(&err)));
+                }
+                return Ok((cli::EXIT_SUCCESS, config_debug));
+            },
+            Ok(false) => log_info!("Steam did not identify itself after restarting. Continuing without restarting."),
+            Err(err) => log_warn!("Could not record the Steam restart: {}", describe(&err)),
+        }
+    }
+    match FallbackRecord::take(&program_directory) {
+        Ok(Some(record)) => log_warn!("Launch request {} bypassed Steam: {}", record.request_id, record.reason),
+        Ok(None) => (),
+        Err(err) => log_warn!("Could not read the failed launch record: {}", describe(&err)),
     }
-    let current_version_directory = match get_newest_roblox_player_directory_path(&program_directory) {
-        Some(v) => v,
-        None => return Err(UIErr::Simple("Error getting Roblox newest directory")),
+    commands::recover_interrupted(paths, false)?;
+    let request_ttl = Duration::from_secs(config.launch_request_ttl_secs);
+    let receipt = match receive_launch_request(&program_directory, request_ttl, HANDOFF_CONNECT_TIMEOUT) {
+        Ok(receipt) => receipt,
+        Err(err) => return Err(UIErr::Handoff("Could not receive launch request", err)),
     };
-    if config_debug {
-        println!("Got current version directory: {:?}", current_version_directory);
-    }
-    let current_version = match current_version_directory.file_name() {
-        Some(v) => v,
-        None => return Err(UIErr::Simple("Error getting newest version name from directory"))
-    }.to_string_lossy().into_owned();
-    if config_debug {
-        println!("Got current version: {:?}", current_version);
-    }
-    match check_if_newest_version(current_version) {
-        Ok(false) => {
-            if config_debug {
-                println!("Roblox is not the newest version. Updating...");
+    for dropped in &receipt.dropped {
+        log_warn!("Dropped launch request {}: {}", dropped.id, dropped.reason);
+    }
+    if receipt.request.is_none() && receipt.dropped.iter().any(|dropped| matches!(dropped.reason, DropReason::Incompatible { .. })) {
+        return Err(UIErr::Simple("Roblox was started through an incompatible substitute. Please update all Roblox Steam Launcher files."));
+    }
+    let config_arguments = match receipt.request {
+        Some(request) => {
+            log_info!("Received launch request {}", request.id);
+            for argument in &request.arguments {
+                if let Some(uri) = argument.uri() {
+                    log_info!("Launch mode: {:?}, place: {:?}, job: {:?}", uri.launch_mode(), uri.place_id(), uri.job_id());
+                    log_debug!("Launch URI: {}", uri.to_redacted_string());
+                }
             }
+            request.arguments
+        },
+        None => vec![],
+    };
+    if let Err(err) = check_arguments(&config_arguments) {
+        if !config.allow_unrecognized_arguments {
+            return Err(UIErr::String("Refused to launch Roblox with unexpected arguments", err.to_string()));
+        }
+        log_warn!("Launching anyway because allow_unrecognized_arguments is set: {}", err);
+    }
+    let current_version_info = match find_newest_player_version(&program_directory, VersionOrder::Created) {
+        Ok(Some(v)) => v,
+        Ok(None) => return Err(UIErr::Simple("Could not find a Roblox player version directory")),
+        Err(err) => return Err(UIErr::String("Error getting Roblox newest directory", describe(&err))),
+    };
+    let current_version_directory = current_version_info.path;
+    log_debug!("Got current version directory: {:?}", current_version_directory);
+    let current_version = current_version_info.version;
+    log_info!("Got current version: {:?}", current_version);
+    let update_status = if config.update_check.background {
+        let pending = check_for_update_in_background(config.update_check.clone(), current_version.clone(), program_directory.clone());
+        apply_launcher(&current_version_directory, &substitute_path, "Error applying launcher")?;
+        match pending.recv_timeout(BACKGROUND_UPDATE_CHECK_GRACE) {
+            Ok(status) => status,
+            Err(_) => UpdateStatus::Unknown { reason: String::from("the update check did not finish in time") },
+        }
+    } else {
+        check_for_update_cached(&config.update_check, &current_version, &program_directory)
+    };
+    log_info!("Update status: {:?}", update_status);
+    let code = match update_status {
+        UpdateStatus::Outdated { .. } => {
+            log_info!("Roblox is not the newest version. Updating...");
             // Remove existing modifications
-            if let ApplyResult::Error(err) = unapply_launcher(&current_version_directory) {
+            if let Err(err) = unapply(&current_version_directory) {
                 return Err(UIErr::Apply("Error unapplying launcher for update", err));
             }
-            if config_debug {
-                println!("Unapplied existing launcher.");
-            }
+            log_debug!("Unapplied existing launcher.");
             // Run roblox once to update, and wait for it to close.
             // In the future, we should only run it once, but replace the new exe as it is created.
             let mut exe_path = current_version_directory.clone();
             exe_path.push("RobloxPlayerLauncher.exe");
+            let existing = snapshot_processes(platform, &config);
             match launch_game(&exe_path, &config_arguments) {
-                Ok(_) => {
-                    if config_debug {
-                        println!("Began update process.");
+                Ok(updater) => {
+                    log_debug!("Began update process.");
+                    match watch_for_new_exe(&program_directory, &current_version_directory) {
+                        WatchOutcome::Ready(new_launcher_path) => {
+                            log_debug!("New launcher at: {:?}", new_launcher_path);
+                            let mut new_version_directory = new_launcher_path;
+                            new_version_directory.pop();
+                            apply_launcher(&new_version_directory, &substitute_path, "Error applying launcher to the updated version")?;
+                        },
+                        WatchOutcome::TimedOut => {
+                            // Roblox still starts. The launcher is applied the next time we run.
+                            log_warn!("Timed out waiting for the update. The launcher will be applied next time.");
+                        },
+                        WatchOutcome::Failed(reason) => return Err(UIErr::String("Could not watch for the Roblox update", reason)),
                     }
-                    watch_for_new_exe(&program_directory, config_debug);
+                    supervise_launch(platform, existing, updater)
                 },
-                Err(err) => return Err(UIErr::String("Could not run the Roblox updater", format!("{:?}", err))),
+                Err(err) => return Err(UIErr::String("Could not run the Roblox updater", describe(&err))),
             }
         },
-        Ok(true) | Err(_) => {
+        UpdateStatus::UpToDate | UpdateStatus::Unknown { .. } => {
             let newest_version_directory = current_version_directory;
-            match apply_launcher(&newest_version_directory) {
-                ApplyResult::AlreadyApplied | ApplyResult::Applied => (),
-                ApplyResult::Error(err) => return Err(UIErr::Apply("Error applying launcher", err)),
-            }
+            apply_launcher(&newest_version_directory, &substitute_path, "Error applying launcher")?;
             if config_arguments.len() == 0 {
-                if config_debug {
-                    println!("Arguments length was 0, exiting.");
-                }
-                return Ok(config_debug);  // We weren't supposed to run the roblox launcher anyway
+                log_debug!("Arguments length was 0, exiting.");
+                return Ok((cli::EXIT_SUCCESS, config_debug));  // We weren't supposed to run the roblox launcher anyway
             }
             let mut game_directory = newest_version_directory;
             game_directory.push("RobloxPlayerLauncher_original.exe");
-            if let Err(err) = launch_game(&game_directory, &config_arguments) {
-                return Err(UIErr::String("Could not run Roblox", format!("{:?}", err)));
-            }
+            let existing = snapshot_processes(platform, &config);
+            let launcher = match launch_game(&game_directory, &config_arguments) {
+                Ok(launcher) => launcher,
+                Err(err) => return Err(UIErr::String("Could not run Roblox", describe(&err))),
+            };
+            log_info!("Launched Roblox");
+            supervise_launch(platform, existing, launcher)
         },
     };
-    Ok(config_debug)
+    Ok((code, config_debug))
+}
+
+/// Applies the launcher to `version_directory`. Updating an older substitute fails while it is
+/// still running, and as it still works that only warrants a warning. Failing to apply the
+/// launcher at all fails the launch.
+fn apply_launcher(version_directory: &Path, substitute_path: &Path, reason: &'static str) -> Result<(), UIErr> {
+    let err = match apply(version_directory, substitute_path) {
+        Ok(outcome) => {
+            log_info!("Applied launcher to {}: {:?}", version_directory.display(), outcome);
+            return Ok(());
+        },
+        Err(err) => err,
+    };
+    if let Ok(InstallState::Applied) = InstallState::of(version_directory) {
+        log_warn!("Could not update the launcher in {}, keeping the one there: {}", version_directory.display(), describe(&err));
+        return Ok(());
+    }
+    Err(UIErr::Apply(reason, err))
+}
+
+/// Lists the processes running before Roblox is launched, so the client it starts can be told
+/// apart from any that were already running. Returns `None` if not supervising.
+fn snapshot_processes(platform: &dyn Platform, config: &Config) -> Option<Vec<ProcessInfo>> {
+    if !config.supervise {
+        return None;
+    }
+    match platform.processes() {
+        Ok(processes) => Some(processes),
+        Err(err) => {
+            log_warn!("Not supervising Roblox because processes could not be listed: {}", describe(&err));
+            None
+        },
+    }
+}
+
+/// Waits for the Roblox client started by `launcher` to exit if there is a process snapshot to
+/// supervise it with, and returns the exit code to pass on.
+fn supervise_launch(platform: &dyn Platform, existing: Option<Vec<ProcessInfo>>, mut launcher: Child) -> i32 {
+    let existing = match existing {
+        Some(existing) => existing,
+        None => return cli::EXIT_SUCCESS,
+    };
+    match supervise(platform, &mut launcher, &existing) {
+        Ok(supervised) => supervised.exit_code(),
+        Err(err) => {
+            // Roblox is already running, so this isn't worth failing the launch over.
+            log_warn!("Stopped supervising Roblox: {}", describe(&err));
+            cli::EXIT_SUCCESS
+        },
+    }
 }
 
 fn main() {
-    match errorable_main() {
-        Ok(is_debug) => {
-            if is_debug {
-                println!("Press enter to exit.");
-                std::io::stdin().read_line(&mut String::new()).expect("Failed to read line");
-                return;
-            }
+    let arguments = get_intended_arguments(&mut std::env::args());
+    if print_if_requested("roblox_steam_launcher_master", env!("CARGO_PKG_VERSION"), &arguments) {
+        return;
+    }
+    let options = match cli::parse(&arguments) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            eprintln!();
+            eprintln!("{}", cli::USAGE);
+            std::process::exit(cli::EXIT_USAGE);
         },
+    };
+    let (code, pause) = match errorable_main(&options) {
+        Ok(finished) => finished,
         Err(err) => {
-            println!("Error launching Roblox!");
-            match err {
-                UIErr::Simple(reason) => println!("{}", reason),
-                UIErr::String(reason, _) => println!("{}", reason),
-                UIErr::ConfigRead(reason, _) => println!("{}", reason),
-                UIErr::ConfigWrite(reason, _) => println!("{}", reason),
-                UIErr::Apply(reason, _) => println!("{}", reason),
+            // The error is printed below, so it only needs to go to the log file.
+            logging::set_console_level(None);
+            log_error!("{}", err);
+            if options.json {
+                println!("{}", json!({ "error": err.reason(), "detail": err.detail() }));
+            } else {
+                if options.command == Command::Launch {
+                    println!("Error launching Roblox!");
+                }
+                println!("{}", err);
+                println!();
             }
-            println!();
-            println!("Press enter to exit.");
-            std::io::stdin().read_line(&mut String::new()).expect("Failed to read line");
+            (err.exit_code(), true)
+        },
+    };
+    if pause && !options.no_pause && !options.json {
+        println!("Press enter to exit.");
+        std::io::stdin().read_line(&mut String::new()).expect("Failed to read line");
+    }
+    std::process::exit(code);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use roblox_steam_launcher_shared::platform::FakePlatform;
+    use std::fs;
+
+    fn test_paths(name: &str, config: &Config) -> Paths {
+        let program_directory = std::env::temp_dir().join(format!("roblox_steam_launcher_master_test_{}_{}", std::process::id(), name));
+        fs::create_dir_all(&program_directory).unwrap();
+        let paths = Paths {
+            config_path: program_directory.join(get_config_file_name()),
+            substitute_path: program_directory.join(SUBSTITUTE_FILE_NAME),
+            program_directory,
+        };
+        config.write_to_path(&paths.config_path).unwrap();
+        paths
+    }
+
+    #[test]
+    fn bounces_through_steam() {
+        if started_by_steam() {
             return;
         }
+        let mut config = Config::new();
+        config.steam_gameid = String::from("123");
+        config.bounce_through_steam = true;
+        let paths = test_paths("bounce", &config);
+        let platform = FakePlatform::new();
+
+        assert_eq!(launch(&paths, &platform).unwrap(), (cli::EXIT_SUCCESS, false));
+        assert_eq!(platform.opened_urls(), vec![String::from("steam://rungameid/123")]);
+        fs::remove_dir_all(&paths.program_directory).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn launches_handed_off_request() {
+        use roblox_steam_launcher_shared::install::{ExecutableKind, SUBSTITUTE_MARKER, SUBSTITUTE_VERSION_TAG};
+        use roblox_steam_launcher_shared::versions::LAUNCHER_FILE_NAME;
+        use std::os::unix::fs::PermissionsExt;
+        use std::thread;
+
+        let mut config = Config::new();
+        // Nothing listens here, so the update check fails at once and Roblox counts as current.
+        config.update_check.base_url = String::from("http://127.0.0.1:1");
+        let paths = test_paths("launch", &config);
+        let version = paths.program_directory.join("version-abc");
+        fs::create_dir_all(&version).unwrap();
+        // Stands in for Roblox's launcher, recording what it was started with.
+        let launcher = version.join(LAUNCHER_FILE_NAME);
+        fs::write(&launcher, "#!/bin/sh\necho \"$@\" > \"$(dirname \"$0\")/launched\"\n").unwrap();
+        fs::set_permissions(&launcher, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(&paths.substitute_path, [SUBSTITUTE_MARKER, SUBSTITUTE_VERSION_TAG].concat()).unwrap();
+
+        let uri = "roblox-player:1+launchmode:play";
+        let server = HandoffServer::start(&paths.program_directory, LaunchRequest::new(vec![String::from(uri)])).unwrap();
+        let substitute = thread::spawn(move || server.wait_for_ack(Duration::from_secs(10)));
+        assert_eq!(launch(&paths, &FakePlatform::new()).unwrap(), (cli::EXIT_SUCCESS, false));
+        substitute.join().unwrap().unwrap();
+
+        assert_eq!(ExecutableKind::of(&launcher).unwrap(), ExecutableKind::Substitute);
+        let launched = version.join("launched");
+        for _ in 0..100 {
+            if let Ok(arguments) = fs::read_to_string(&launched) {
+                assert_eq!(arguments, format!("{}\n", uri));
+                fs::remove_dir_all(&paths.program_directory).unwrap();
+                return;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        panic!("Roblox was not launched");
     }
 }
@@ -0,0 +1,401 @@
+//! Asks Roblox's deployment servers for the latest version of the player, and remembers the
+//! answer for a while so most launches don't wait on the network.
+
+use roblox_steam_launcher_shared::{get_update_cache_file_name, unix_time_now, write_file_atomically, UpdateCheckConfig, UpdateEndpointFormat};
+
+use hyper;
+use hyper::client::Client;
+use hyper::net::{HttpStream, HttpsConnector, NetworkStream, SslClient};
+use hyper::status::StatusCode;
+use native_tls;
+use native_tls::{HandshakeError, TlsConnector};
+use serde_json;
+use serde_json::Value;
+
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::Duration;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UpdateStatus {
+    UpToDate,
+    Outdated { latest: String },
+    /// The latest version could not be found out, so Roblox may or may not need an update.
+    Unknown { reason: String },
+}
+
+/// Returns the URL that reports the latest version for `config`'s format and channel.
+pub fn get_update_url(config: &UpdateCheckConfig) -> String {
+    let base_url = match config.base_url.trim_end_matches('/') {
+        "" => config.format.default_base_url(),
+        base_url => base_url,
+    };
+    let live = config.channel.is_empty() || config.channel.eq_ignore_ascii_case("live");
+    match config.format {
+        UpdateEndpointFormat::ClientVersion if live => format!("{}/v2/client-version/WindowsPlayer", base_url),
+        UpdateEndpointFormat::ClientVersion => format!("{}/v2/client-version/WindowsPlayer/channel/{}", base_url, config.channel),
+        UpdateEndpointFormat::LegacyText if live => format!("{}/version", base_url),
+        UpdateEndpointFormat::LegacyText => format!("{}/channel/{}/version", base_url, config.channel),
+    }
+}
+
+/// Extracts the version name, such as `version-0123456789abcdef`, from an endpoint's answer.
+fn parse_latest_version(format: UpdateEndpointFormat, body: &str) -> Result<String, String> {
+    let version = match format {
+        UpdateEndpointFormat::LegacyText => String::from(body.trim()),
+        UpdateEndpointFormat::ClientVersion => match serde_json::from_str::<Value>(body) {
+            Ok(document) => match document.get("clientVersionUpload") {
+                Some(Value::String(version)) => version.clone(),
+                _ => return Err(String::from("answer has no clientVersionUpload")),
+            },
+            Err(err) => return Err(format!("answer is not JSON: {}", err)),
+        },
+    };
+    if version.is_empty() {
+        return Err(String::from("answer is empty"));
+    }
+    Ok(version)
+}
+
+/// A TLS connection that hyper can share between the request and the response.
+#[derive(Clone)]
+struct TlsStream(Arc<Mutex<native_tls::TlsStream<HttpStream>>>);
+
+impl TlsStream {
+    fn lock(&self) -> io::Result<MutexGuard<'_, native_tls::TlsStream<HttpStream>>> {
+        self.0.lock().map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "the TLS connection was poisoned"))
+    }
+}
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.lock()?.read(buf)
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.lock()?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.lock()?.flush()
+    }
+}
+
+impl NetworkStream for TlsStream {
+    fn peer_addr(&mut self) -> io::Result<SocketAddr> {
+        self.lock()?.get_mut().peer_addr()
+    }
+
+    fn set_read_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.lock()?.get_ref().set_read_timeout(dur)
+    }
+
+    fn set_write_timeout(&self, dur: Option<Duration>) -> io::Result<()> {
+        self.lock()?.get_ref().set_write_timeout(dur)
+    }
+
+    fn close(&mut self, how: Shutdown) -> io::Result<()> {
+        self.lock()?.get_mut().close(how)
+    }
+}
+
+/// Wraps `https` connections in the system's TLS implementation.
+struct Tls(TlsConnector);
+
+impl SslClient for Tls {
+    type Stream = TlsStream;
+
+    fn wrap_client(&self, stream: HttpStream, host: &str) -> hyper::Result<TlsStream> {
+        match self.0.connect(host, stream) {
+            Ok(stream) => Ok(TlsStream(Arc::new(Mutex::new(stream)))),
+            Err(HandshakeError::Failure(err)) => Err(hyper::Error::Ssl(Box::new(err))),
+            Err(HandshakeError::WouldBlock(_)) => Err(hyper::Error::Io(io::Error::new(io::ErrorKind::TimedOut, "the TLS handshake timed out"))),
+        }
+    }
+}
+
+fn fetch(url: &str, config: &UpdateCheckConfig) -> Result<String, String> {
+    let connect_timeout = Duration::from_secs(config.connect_timeout_secs);
+    let read_timeout = Duration::from_secs(config.read_timeout_secs);
+    let tls = match TlsConnector::new() {
+        Ok(tls) => Tls(tls),
+        Err(err) => return Err(format!("cannot set up TLS: {}", err)),
+    };
+    let mut client = Client::with_connector(HttpsConnector::with_connector(tls, move |host: &str, port: u16, _scheme: &str| -> io::Result<TcpStream> {
+        let mut last_err = io::Error::new(io::ErrorKind::NotFound, format!("{} has no addresses", host));
+        for address in (host, port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, connect_timeout) {
+                Ok(stream) => {
+                    // The TLS handshake happens before hyper applies its own timeouts.
+                    stream.set_read_timeout(Some(read_timeout))?;
+                    stream.set_write_timeout(Some(read_timeout))?;
+                    return Ok(stream);
+                },
+                Err(err) => last_err = err,
+            }
+        }
+        Err(last_err)
+    }));
+    client.set_read_timeout(Some(read_timeout));
+    client.set_write_timeout(Some(read_timeout));
+    let mut res = match client.get(url).send() {
+        Ok(res) => res,
+        Err(err) => return Err(format!("cannot reach {}: {}", url, err)),
+    };
+    if res.status != StatusCode::Ok {
+        return Err(format!("{} answered {}", url, res.status));
+    }
+    let mut body = String::new();
+    if let Err(err) = res.read_to_string(&mut body) {
+        return Err(format!("cannot read the answer from {}: {}", url, err));
+    }
+    Ok(body)
+}
+
+fn compare_versions(latest: String, current_version: &str) -> UpdateStatus {
+    if latest.eq_ignore_ascii_case(current_version.trim()) {
+        UpdateStatus::UpToDate
+    } else {
+        UpdateStatus::Outdated { latest }
+    }
+}
+
+/// Asks `url` for the latest version of the player.
+fn fetch_latest_version(url: &str, config: &UpdateCheckConfig) -> Result<String, String> {
+    fetch(url, config).and_then(|body| parse_latest_version(config.format, &body))
+}
+
+/// The last version an update check found to be the latest.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+struct UpdateCache {
+    /// The URL that was asked, so changing the endpoint or channel invalidates the cache.
+    url: String,
+    latest: String,
+    /// When the check was made, in seconds since the Unix epoch.
+    checked: u64,
+}
+
+fn get_update_cache_path(directory: &Path) -> PathBuf {
+    directory.join(get_update_cache_file_name())
+}
+
+fn read_update_cache(directory: &Path) -> Option<UpdateCache> {
+    let contents = fs::read_to_string(get_update_cache_path(directory)).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn write_update_cache(directory: &Path, cache: &UpdateCache) -> io::Result<()> {
+    let contents = match serde_json::to_string(cache) {
+        Ok(contents) => contents,
+        Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+    };
+    write_file_atomically(&get_update_cache_path(directory), contents.as_bytes())
+}
+
+/// Checks whether `current_version` is the latest version of the player. The cache in `directory`
+/// is trusted instead if it says `current_version` was the latest less than `cache_ttl_secs` ago.
+/// A cached version that differs from `current_version` is always checked again, since Roblox may
+/// have updated itself since.
+pub fn check_for_update_cached(config: &UpdateCheckConfig, current_version: &str, directory: &Path) -> UpdateStatus {
+    let url = get_update_url(config);
+    let now = unix_time_now();
+    if let Some(cache) = read_update_cache(directory) {
+        let fresh = cache.checked <= now && now - cache.checked < config.cache_ttl_secs;
+        if fresh && cache.url == url && cache.latest.eq_ignore_ascii_case(current_version.trim()) {
+            return UpdateStatus::UpToDate;
+        }
+    }
+    check_for_update_now(config, current_version, directory)
+}
+
+/// Checks whether `current_version` is the latest version of the player without trusting the
+/// cache in `directory`, and refreshes the cache with the answer.
+pub fn check_for_update_now(config: &UpdateCheckConfig, current_version: &str, directory: &Path) -> UpdateStatus {
+    let url = get_update_url(config);
+    let latest = match fetch_latest_version(&url, config) {
+        Ok(latest) => latest,
+        Err(reason) => return UpdateStatus::Unknown { reason },
+    };
+    // A cache that can't be written only costs a network check next time.
+    let _ = write_update_cache(directory, &UpdateCache { url, latest: latest.clone(), checked: unix_time_now() });
+    compare_versions(latest, current_version)
+}
+
+/// Runs `check_for_update_cached` on another thread. The result arrives on the returned channel.
+pub fn check_for_update_in_background(config: UpdateCheckConfig, current_version: String, directory: PathBuf) -> Receiver<UpdateStatus> {
+    let (tx, rx) = channel();
+    thread::spawn(move || {
+        let _ = tx.send(check_for_update_cached(&config, &current_version, &directory));
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Answers one request on a local port and returns the base URL and the request line it got.
+    fn serve_once(status: &'static str, body: &'static str) -> (String, thread::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            loop {
+                let mut header = String::new();
+                if reader.read_line(&mut header).unwrap() == 0 || header == "\r\n" {
+                    break;
+                }
+            }
+            let mut stream = reader.into_inner();
+            write!(stream, "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, body.len(), body).unwrap();
+            String::from(request_line.trim())
+        });
+        (base_url, handle)
+    }
+
+    fn config(format: UpdateEndpointFormat, base_url: String, channel: &str) -> UpdateCheckConfig {
+        UpdateCheckConfig {
+            format,
+            base_url,
+            channel: String::from(channel),
+            connect_timeout_secs: 1,
+            read_timeout_secs: 1,
+            cache_ttl_secs: 60,
+            background: false,
+        }
+    }
+
+    fn check_for_update(config: &UpdateCheckConfig, current_version: &str) -> UpdateStatus {
+        match fetch_latest_version(&get_update_url(config), config) {
+            Ok(latest) => compare_versions(latest, current_version),
+            Err(reason) => UpdateStatus::Unknown { reason },
+        }
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("roblox_steam_launcher_update_test_{}_{}", std::process::id(), name));
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn defaults_to_each_format_host() {
+        let client_version = UpdateCheckConfig { format: UpdateEndpointFormat::ClientVersion, ..UpdateCheckConfig::default() };
+        assert_eq!(get_update_url(&client_version), "https://clientsettingscdn.roblox.com/v2/client-version/WindowsPlayer");
+        let zcanary = UpdateCheckConfig { channel: String::from("zcanary"), ..client_version };
+        assert_eq!(get_update_url(&zcanary), "https://clientsettingscdn.roblox.com/v2/client-version/WindowsPlayer/channel/zcanary");
+        assert_eq!(get_update_url(&UpdateCheckConfig::default()), "https://setup.roblox.com/version");
+    }
+
+    #[test]
+    fn checks_legacy_text_endpoint() {
+        let (base_url, server) = serve_once("200 OK", "version-abc\n");
+        let status = check_for_update(&config(UpdateEndpointFormat::LegacyText, base_url, "live"), "version-abc");
+        assert_eq!(status, UpdateStatus::UpToDate);
+        assert_eq!(server.join().unwrap(), "GET /version HTTP/1.1");
+
+        let (base_url, server) = serve_once("200 OK", "version-def");
+        let status = check_for_update(&config(UpdateEndpointFormat::LegacyText, base_url, "zcanary"), "version-abc");
+        assert_eq!(status, UpdateStatus::Outdated { latest: String::from("version-def") });
+        assert_eq!(server.join().unwrap(), "GET /channel/zcanary/version HTTP/1.1");
+    }
+
+    #[test]
+    fn checks_client_version_endpoint() {
+        let (base_url, server) = serve_once("200 OK", r#"{"version":"0.400.0.1","clientVersionUpload":"version-def","bootstrapperVersion":"1, 6, 0, 1"}"#);
+        let status = check_for_update(&config(UpdateEndpointFormat::ClientVersion, base_url, "live"), "version-abc");
+        assert_eq!(status, UpdateStatus::Outdated { latest: String::from("version-def") });
+        assert_eq!(server.join().unwrap(), "GET /v2/client-version/WindowsPlayer HTTP/1.1");
+
+        let (base_url, server) = serve_once("200 OK", r#"{"version":"0.400.0.1"}"#);
+        match check_for_update(&config(UpdateEndpointFormat::ClientVersion, base_url, "live"), "version-abc") {
+            UpdateStatus::Unknown { .. } => (),
+            other => panic!("expected an unknown status, got {:?}", other),
+        }
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn failures_are_unknown() {
+        let (base_url, server) = serve_once("503 Service Unavailable", "");
+        match check_for_update(&config(UpdateEndpointFormat::LegacyText, base_url, "live"), "version-abc") {
+            UpdateStatus::Unknown { ref reason } => assert!(reason.contains("503"), "{}", reason),
+            other => panic!("expected an unknown status, got {:?}", other),
+        }
+        server.join().unwrap();
+
+        // A server that doesn't speak TLS fails the handshake.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("https://{}", listener.local_addr().unwrap());
+        let server = thread::spawn(move || drop(listener.accept().unwrap()));
+        match check_for_update(&config(UpdateEndpointFormat::LegacyText, base_url, "live"), "version-abc") {
+            UpdateStatus::Unknown { .. } => (),
+            other => panic!("expected an unknown status, got {:?}", other),
+        }
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn read_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        // Accept the connection but never answer.
+        let server = thread::spawn(move || {
+            let _stream = listener.accept().unwrap();
+            thread::sleep(Duration::from_secs(2));
+        });
+        match check_for_update(&config(UpdateEndpointFormat::LegacyText, base_url, "live"), "version-abc") {
+            UpdateStatus::Unknown { .. } => (),
+            other => panic!("expected an unknown status, got {:?}", other),
+        }
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn cache_skips_network_while_fresh() {
+        let dir = test_dir("cache");
+        let (base_url, server) = serve_once("200 OK", "version-abc");
+        let config = config(UpdateEndpointFormat::LegacyText, base_url, "live");
+        assert_eq!(check_for_update_cached(&config, "version-abc", &dir), UpdateStatus::UpToDate);
+        server.join().unwrap();
+
+        // The server is gone, so only the cache can answer.
+        assert_eq!(check_for_update_cached(&config, "version-abc", &dir), UpdateStatus::UpToDate);
+        match check_for_update_cached(&config, "version-old", &dir) {
+            UpdateStatus::Unknown { .. } => (),
+            other => panic!("expected a network check, got {:?}", other),
+        }
+        let mut expired = read_update_cache(&dir).unwrap();
+        expired.checked -= config.cache_ttl_secs;
+        write_update_cache(&dir, &expired).unwrap();
+        match check_for_update_cached(&config, "version-abc", &dir) {
+            UpdateStatus::Unknown { .. } => (),
+            other => panic!("expected a network check, got {:?}", other),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn checks_in_background() {
+        let dir = test_dir("background");
+        let (base_url, server) = serve_once("200 OK", "version-def");
+        let pending = check_for_update_in_background(config(UpdateEndpointFormat::LegacyText, base_url, "live"), String::from("version-abc"), dir.clone());
+        assert_eq!(pending.recv_timeout(Duration::from_secs(5)).unwrap(), UpdateStatus::Outdated { latest: String::from("version-def") });
+        server.join().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
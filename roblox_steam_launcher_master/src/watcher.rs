@@ -0,0 +1,241 @@
+//! Watches the `Versions` directory while Roblox updates itself, for the new version's launcher.
+
+use roblox_steam_launcher_shared::versions::LAUNCHER_FILE_NAME;
+
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// How long to wait for the updater to write the new launcher.
+pub const WATCH_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How long the new launcher has to go unchanged before it is considered completely written.
+pub const SETTLE_PERIOD: Duration = Duration::from_secs(2);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum WatchOutcome {
+    /// A new launcher appeared at the given path and has finished being written.
+    Ready(PathBuf),
+    /// No new launcher was finished within the timeout.
+    TimedOut,
+    /// Watching for changes stopped working.
+    Failed(String),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum WatchState {
+    /// Waiting for a new launcher to appear.
+    Waiting,
+    /// A new launcher appeared. It is ready once its size stays the same for the settle period
+    /// and nothing else has it open.
+    Settling { path: PathBuf, size: Option<u64>, changed: Instant },
+}
+
+#[cfg(windows)]
+fn open_exclusively(path: &Path) -> io::Result<fs::File> {
+    use std::os::windows::fs::OpenOptionsExt;
+    // Sharing nothing fails while the updater still has the file open.
+    fs::OpenOptions::new().read(true).write(true).share_mode(0).open(path)
+}
+
+#[cfg(not(windows))]
+fn open_exclusively(path: &Path) -> io::Result<fs::File> {
+    fs::OpenOptions::new().read(true).write(true).open(path)
+}
+
+struct WatchMachine {
+    /// The version directory being replaced, whose launcher doesn't count as new.
+    ignore: PathBuf,
+    deadline: Instant,
+    settle_period: Duration,
+    state: WatchState,
+}
+
+impl WatchMachine {
+    fn new(ignore: &Path, start: Instant, timeout: Duration, settle_period: Duration) -> WatchMachine {
+        WatchMachine {
+            ignore: ignore.to_path_buf(),
+            deadline: start + timeout,
+            settle_period,
+            state: WatchState::Waiting,
+        }
+    }
+
+    fn is_new_launcher(&self, path: &Path) -> bool {
+        path.file_name() == Some(OsStr::new(LAUNCHER_FILE_NAME)) && !path.starts_with(&self.ignore)
+    }
+
+    fn is_settling(&self, path: &Path) -> bool {
+        match self.state {
+            WatchState::Settling { path: ref settling, .. } => settling == path,
+            WatchState::Waiting => false,
+        }
+    }
+
+    /// Notes that the launcher at `path` was created or changed at `now`.
+    fn touch(&mut self, path: &Path, now: Instant) {
+        if let WatchState::Settling { path: ref settling, ref mut changed, .. } = self.state {
+            if settling == path {
+                *changed = now;
+                return;
+            }
+        }
+        self.state = WatchState::Settling { path: path.to_path_buf(), size: None, changed: now };
+    }
+
+    fn on_event(&mut self, event: &DebouncedEvent, now: Instant) {
+        match event {
+            DebouncedEvent::Create(path) | DebouncedEvent::Write(path) | DebouncedEvent::NoticeWrite(path) | DebouncedEvent::Chmod(path) => {
+                if self.is_new_launcher(path) {
+                    self.touch(path, now);
+                }
+            },
+            DebouncedEvent::Rename(from, to) => {
+                if self.is_new_launcher(to) {
+                    self.touch(to, now);
+                } else if self.is_settling(from) {
+                    self.state = WatchState::Waiting;
+                }
+            },
+            DebouncedEvent::Remove(path) | DebouncedEvent::NoticeRemove(path) => {
+                if self.is_settling(path) {
+                    self.state = WatchState::Waiting;
+                }
+            },
+            DebouncedEvent::Rescan | DebouncedEvent::Error(..) => (),
+        }
+    }
+
+    /// Checks on the launcher being settled. Returns the outcome once there is one.
+    fn on_tick(&mut self, now: Instant) -> Option<WatchOutcome> {
+        if now >= self.deadline {
+            return Some(WatchOutcome::TimedOut);
+        }
+        let next_state = match self.state {
+            WatchState::Waiting => return None,
+            WatchState::Settling { ref path, size, changed } => match fs::metadata(path) {
+                Err(_) => WatchState::Waiting,
+                Ok(metadata) => {
+                    if size != Some(metadata.len()) {
+                        WatchState::Settling { path: path.clone(), size: Some(metadata.len()), changed: now }
+                    } else if now.duration_since(changed) >= self.settle_period && open_exclusively(path).is_ok() {
+                        return Some(WatchOutcome::Ready(path.clone()));
+                    } else {
+                        return None;
+                    }
+                },
+            },
+        };
+        self.state = next_state;
+        None
+    }
+}
+
+/// Watches `versions_path` until the updater has finished writing a launcher outside of
+/// `old_version_path`, or until `WATCH_TIMEOUT` passes.
+pub fn watch_for_new_exe(versions_path: &Path, old_version_path: &Path) -> WatchOutcome {
+    // Events name paths under the watched directory as it was given, so both are made canonical
+    // for the old version's paths to be recognised however they were spelled.
+    let versions_path = fs::canonicalize(versions_path).unwrap_or_else(|_| versions_path.to_path_buf());
+    let old_version_path = fs::canonicalize(old_version_path).unwrap_or_else(|_| old_version_path.to_path_buf());
+    let (tx, rx) = channel();
+    let mut watcher: RecommendedWatcher = match Watcher::new(tx, Duration::from_secs(0)) {
+        Ok(watcher) => watcher,
+        Err(err) => return WatchOutcome::Failed(format!("Could not create file watcher: {}", err)),
+    };
+    if let Err(err) = watcher.watch(&versions_path, RecursiveMode::Recursive) {
+        return WatchOutcome::Failed(format!("Could not watch directory: {}", err));
+    }
+    log_debug!("Watching for changes...");
+    let mut machine = WatchMachine::new(&old_version_path, Instant::now(), WATCH_TIMEOUT, SETTLE_PERIOD);
+    loop {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(event) => {
+                log_debug!("{:?}", event);
+                machine.on_event(&event, Instant::now());
+            },
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => return WatchOutcome::Failed(String::from("The file watcher stopped")),
+        }
+        if let Some(outcome) = machine.on_tick(Instant::now()) {
+            return outcome;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("roblox_steam_launcher_watcher_test_{}_{}", std::process::id(), name));
+        fs::create_dir_all(path.join("version-old")).unwrap();
+        fs::create_dir_all(path.join("version-new")).unwrap();
+        path
+    }
+
+    #[test]
+    fn waits_for_launcher_to_settle() {
+        let dir = test_dir("settle");
+        let launcher = dir.join("version-new").join(LAUNCHER_FILE_NAME);
+        let start = Instant::now();
+        let second = Duration::from_secs(1);
+        let mut machine = WatchMachine::new(&dir.join("version-old"), start, Duration::from_secs(60), 2 * second);
+
+        // The old version's launchers are ignored.
+        machine.on_event(&DebouncedEvent::Create(dir.join("version-old").join(LAUNCHER_FILE_NAME)), start);
+        machine.on_event(&DebouncedEvent::Create(dir.join("version-old").join("backup").join(LAUNCHER_FILE_NAME)), start);
+        assert_eq!(machine.state, WatchState::Waiting);
+
+        fs::write(&launcher, "partial").unwrap();
+        machine.on_event(&DebouncedEvent::Create(launcher.clone()), start);
+        assert_eq!(machine.on_tick(start), None);
+        assert_eq!(machine.on_tick(start + second), None);
+
+        // Still being written, so the settle period starts over.
+        fs::write(&launcher, "partial and more").unwrap();
+        assert_eq!(machine.on_tick(start + 2 * second), None);
+        assert_eq!(machine.on_tick(start + 3 * second), None);
+        assert_eq!(machine.on_tick(start + 4 * second), Some(WatchOutcome::Ready(launcher.clone())));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn follows_renames_and_removals() {
+        let dir = test_dir("rename");
+        let temporary = dir.join("version-new").join("download.tmp");
+        let launcher = dir.join("version-new").join(LAUNCHER_FILE_NAME);
+        let start = Instant::now();
+        let mut machine = WatchMachine::new(&dir.join("version-old"), start, Duration::from_secs(60), Duration::from_secs(0));
+
+        fs::write(&launcher, "launcher").unwrap();
+        machine.on_event(&DebouncedEvent::Rename(temporary, launcher.clone()), start);
+        assert!(machine.is_settling(&launcher));
+        machine.on_event(&DebouncedEvent::Remove(launcher.clone()), start);
+        assert_eq!(machine.state, WatchState::Waiting);
+
+        machine.on_event(&DebouncedEvent::Write(launcher.clone()), start);
+        assert_eq!(machine.on_tick(start), None);
+        assert_eq!(machine.on_tick(start), Some(WatchOutcome::Ready(launcher)));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn times_out() {
+        let dir = test_dir("timeout");
+        let start = Instant::now();
+        let mut machine = WatchMachine::new(&dir.join("version-old"), start, Duration::from_secs(60), SETTLE_PERIOD);
+        assert_eq!(machine.on_tick(start + Duration::from_secs(59)), None);
+        assert_eq!(machine.on_tick(start + Duration::from_secs(60)), Some(WatchOutcome::TimedOut));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,135 @@
+//! What each executable supports, printed by `--version` and `--capabilities`, so mismatched
+//! builds can be caught before they are installed or run together.
+
+use serde_json;
+
+use config::CONFIG_SCHEMA_VERSION;
+use error::{Context, Error};
+use install::SUBSTITUTE_VERSION;
+use ipc::{MIN_PROTOCOL_VERSION, PROTOCOL_VERSION};
+
+use std::fmt;
+use std::path::Path;
+use std::process::Command;
+
+pub const VERSION_FLAG: &str = "--version";
+pub const CAPABILITIES_FLAG: &str = "--capabilities";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub name: String,
+    pub version: String,
+    /// The handoff protocol version it speaks.
+    pub protocol_version: u32,
+    /// The oldest handoff protocol version it understands.
+    pub min_protocol_version: u32,
+    /// The newest config schema version it can read.
+    pub config_schema_version: u32,
+    pub substitute_version: u32,
+}
+
+#[derive(Debug)]
+pub enum CapabilitiesError {
+    NotStarted(Error),
+    Failed(String),
+    Malformed(Error),
+    Incompatible(String),
+}
+
+impl fmt::Display for CapabilitiesError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CapabilitiesError::NotStarted(_) => write!(formatter, "Could not run it to ask for its capabilities"),
+            CapabilitiesError::Failed(status) => write!(formatter, "It did not report its capabilities: {}", status),
+            CapabilitiesError::Malformed(_) => write!(formatter, "It reported malformed capabilities"),
+            CapabilitiesError::Incompatible(reason) => write!(formatter, "Incompatible versions: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for CapabilitiesError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CapabilitiesError::NotStarted(err) | CapabilitiesError::Malformed(err) => Some(err),
+            CapabilitiesError::Failed(_) | CapabilitiesError::Incompatible(_) => None,
+        }
+    }
+}
+
+impl Capabilities {
+    /// The capabilities of the executable `name` at `version`, built from this tree.
+    pub fn current(name: &str, version: &str) -> Capabilities {
+        Capabilities {
+            name: String::from(name),
+            version: String::from(version),
+            protocol_version: PROTOCOL_VERSION,
+            min_protocol_version: MIN_PROTOCOL_VERSION,
+            config_schema_version: CONFIG_SCHEMA_VERSION,
+            substitute_version: SUBSTITUTE_VERSION,
+        }
+    }
+
+    /// Runs `executable --capabilities` and reads what it prints.
+    pub fn query(executable: &Path) -> Result<Capabilities, CapabilitiesError> {
+        let output = Command::new(executable).arg(CAPABILITIES_FLAG).output().context("run", executable).map_err(CapabilitiesError::NotStarted)?;
+        if !output.status.success() {
+            return Err(CapabilitiesError::Failed(output.status.to_string()));
+        }
+        serde_json::from_slice(&output.stdout).context("parse the capabilities of", executable).map_err(CapabilitiesError::Malformed)
+    }
+
+    pub fn understands_protocol(&self, protocol: u32) -> bool {
+        self.min_protocol_version <= protocol && protocol <= self.protocol_version
+    }
+
+    /// Checks that `self`, a master, can take launch requests from `substitute`, and that both can
+    /// read a config of `config_schema_version`.
+    pub fn check_compatible(&self, substitute: &Capabilities, config_schema_version: u32) -> Result<(), CapabilitiesError> {
+        if !self.understands_protocol(substitute.protocol_version) {
+            return Err(CapabilitiesError::Incompatible(format!("{} {} understands launch protocol {} to {}, but {} {} speaks {}", self.name, self.version, self.min_protocol_version, self.protocol_version, substitute.name, substitute.version, substitute.protocol_version)));
+        }
+        for capabilities in &[self, substitute] {
+            if capabilities.config_schema_version < config_schema_version {
+                return Err(CapabilitiesError::Incompatible(format!("{} {} reads config schema version {} at most, but version {} is needed", capabilities.name, capabilities.version, capabilities.config_schema_version, config_schema_version)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Prints the version or capabilities if they were asked for with the only argument. Returns
+/// whether they were, in which case the executable should do nothing else.
+pub fn print_if_requested(name: &str, version: &str, arguments: &[String]) -> bool {
+    if arguments.len() != 1 {
+        return false;
+    }
+    if arguments[0] == VERSION_FLAG {
+        println!("{} {}", name, version);
+    } else if arguments[0] == CAPABILITIES_FLAG {
+        match serde_json::to_string(&Capabilities::current(name, version)) {
+            Ok(capabilities) => println!("{}", capabilities),
+            Err(err) => panic!("Could not describe capabilities: {}", err),
+        }
+    } else {
+        return false;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_compatibility() {
+        let master = Capabilities::current("master", "1.0.0");
+        let mut substitute = Capabilities::current("substitute", "1.0.0");
+        master.check_compatible(&substitute, CONFIG_SCHEMA_VERSION).unwrap();
+        assert!(master.check_compatible(&substitute, CONFIG_SCHEMA_VERSION + 1).is_err());
+        substitute.protocol_version = PROTOCOL_VERSION + 1;
+        match master.check_compatible(&substitute, CONFIG_SCHEMA_VERSION) {
+            Err(CapabilitiesError::Incompatible(_)) => (),
+            other => panic!("expected incompatible versions, got {:?}", other),
+        }
+    }
+}
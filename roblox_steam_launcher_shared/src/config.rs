@@ -0,0 +1,446 @@
+use serde_json;
+use serde_json::{Map, Value};
+
+use error::{Context, Error};
+use ipc::HANDOFF_TIMEOUT;
+use launch_uri::LaunchArgument;
+use lock::FileLock;
+use steam_launch::SteamLaunchStrategy;
+use write_file_atomically;
+
+use std::fs::OpenOptions;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use std::error::Error as StdError;
+use std::fmt;
+
+/// The schema version written by this build. Bump this and add a step to `MIGRATIONS` only when
+/// existing values have to change to keep their meaning. A new field gets a `#[serde(default)]`
+/// instead, so older builds can still read configs written by newer ones.
+pub const CONFIG_SCHEMA_VERSION: u32 = 2;
+
+/// `MIGRATIONS[n]` upgrades a version `n` document to version `n + 1`.
+const MIGRATIONS: &[fn(&mut Map<String, Value>)] = &[
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+];
+
+// Version 0 is the original unversioned config. Launch arguments are handed to the master over
+// IPC now instead of through the config.
+fn migrate_v0_to_v1(config: &mut Map<String, Value>) {
+    config.remove("arguments");
+}
+
+// An empty base URL means the format's own host now, so the old default no longer pins the JSON
+// endpoint to a host that doesn't serve it.
+fn migrate_v1_to_v2(config: &mut Map<String, Value>) {
+    if let Some(Value::Object(update_check)) = config.get_mut("update_check") {
+        if update_check.get("base_url").and_then(Value::as_str) == Some("http://setup.roblox.com") {
+            update_check.insert(String::from("base_url"), Value::from(""));
+        }
+    }
+}
+
+fn default_update_cache_ttl_secs() -> u64 {
+    60 * 60
+}
+
+fn default_handoff_timeout_secs() -> u64 {
+    HANDOFF_TIMEOUT.as_secs()
+}
+
+fn default_launch_request_ttl_secs() -> u64 {
+    120
+}
+
+/// The format of the endpoint that reports the latest Roblox version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateEndpointFormat {
+    /// `{base_url}/v2/client-version/WindowsPlayer`, which answers with JSON.
+    ClientVersion,
+    /// `{base_url}/version`, which answers with the bare version name.
+    LegacyText,
+}
+
+impl UpdateEndpointFormat {
+    /// The host that serves this format, used when no base URL is configured.
+    pub fn default_base_url(self) -> &'static str {
+        match self {
+            UpdateEndpointFormat::ClientVersion => "https://clientsettingscdn.roblox.com",
+            UpdateEndpointFormat::LegacyText => "https://setup.roblox.com",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UpdateCheckConfig {
+    pub format: UpdateEndpointFormat,
+    /// An `http` or `https` URL. Empty uses the format's default host.
+    #[serde(default)]
+    pub base_url: String,
+    /// The deployment channel, such as `live`.
+    pub channel: String,
+    pub connect_timeout_secs: u64,
+    pub read_timeout_secs: u64,
+    /// How long a version found to be the latest is trusted without asking again.
+    #[serde(default = "default_update_cache_ttl_secs")]
+    pub cache_ttl_secs: u64,
+    /// Whether to check while the launcher is being applied, instead of before. The update is
+    /// only installed if the check has finished by then.
+    #[serde(default)]
+    pub background: bool,
+}
+
+impl Default for UpdateCheckConfig {
+    fn default() -> UpdateCheckConfig {
+        UpdateCheckConfig {
+            format: UpdateEndpointFormat::LegacyText,
+            base_url: String::new(),
+            channel: String::from("live"),
+            connect_timeout_secs: 5,
+            read_timeout_secs: 10,
+            cache_ttl_secs: default_update_cache_ttl_secs(),
+            background: false,
+        }
+    }
+}
+
+#[derive(Clone,Debug,PartialEq,Serialize, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub schema_version: u32,
+    pub steam_gameid: String,
+    /// Logs at the debug level instead of the info level, and has the master print what it does.
+    pub debug: bool,
+    /// Launch requests older than this are dropped by the master instead of being launched.
+    #[serde(default = "default_launch_request_ttl_secs")]
+    pub launch_request_ttl_secs: u64,
+    /// How the substitute asks Steam to start the master.
+    #[serde(default)]
+    pub steam_launch: SteamLaunchStrategy,
+    /// How long the substitute waits for the master before launching Roblox itself.
+    #[serde(default = "default_handoff_timeout_secs")]
+    pub handoff_timeout_secs: u64,
+    /// Whether the master restarts itself through Steam when something else started it, so the
+    /// Steam overlay attaches to the game.
+    #[serde(default)]
+    pub bounce_through_steam: bool,
+    /// Whether the master launches Roblox with arguments other than one launch URI and known
+    /// flags, instead of refusing to.
+    #[serde(default)]
+    pub allow_unrecognized_arguments: bool,
+    /// Whether the master keeps running until the Roblox client exits, so Steam counts the whole
+    /// session as playing, instead of exiting once Roblox has been launched.
+    #[serde(default)]
+    pub supervise: bool,
+    /// Where and how the master checks whether Roblox needs an update.
+    #[serde(default)]
+    pub update_check: UpdateCheckConfig,
+}
+
+#[derive(Debug)]
+pub enum ConfigReadError {
+    Malformed(Error),
+    NotReadable(Error),
+    NotOpenable(Error),
+    /// The config was written by a newer release with the given schema version.
+    TooNew(u32),
+    NotMigratable(Error),
+    NotLockable(Error),
+}
+
+impl fmt::Display for ConfigReadError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigReadError::Malformed(_) => write!(formatter, "Config file is malformed"),
+            ConfigReadError::NotReadable(_) => write!(formatter, "Config file is not readable"),
+            ConfigReadError::NotOpenable(_) => write!(formatter, "Config file cannot be opened"),
+            ConfigReadError::NotMigratable(_) => write!(formatter, "Config file cannot be upgraded to schema version {}", CONFIG_SCHEMA_VERSION),
+            ConfigReadError::NotLockable(_) => write!(formatter, "Config file cannot be locked"),
+            ConfigReadError::TooNew(version) => write!(formatter, "Config file was written by a newer version of Roblox Steam Launcher (schema version {}, this version supports up to {}). Please update all Roblox Steam Launcher files.", version, CONFIG_SCHEMA_VERSION),
+        }
+    }
+}
+
+impl StdError for ConfigReadError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            ConfigReadError::Malformed(err) | ConfigReadError::NotReadable(err) | ConfigReadError::NotOpenable(err) | ConfigReadError::NotMigratable(err) | ConfigReadError::NotLockable(err) => Some(err),
+            ConfigReadError::TooNew(_) => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigWriteError {
+    NotSerializable(Error),
+    NotWriteable(Error),
+}
+
+impl fmt::Display for ConfigWriteError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigWriteError::NotSerializable(_) => write!(formatter, "Config cannot be serialized"),
+            ConfigWriteError::NotWriteable(_) => write!(formatter, "Config file cannot be written"),
+        }
+    }
+}
+
+impl StdError for ConfigWriteError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            ConfigWriteError::NotSerializable(err) | ConfigWriteError::NotWriteable(err) => Some(err),
+        }
+    }
+}
+
+/// Returns the path the pre-migration copy of a version `version` config is saved to.
+pub fn get_config_backup_path(config_path: &Path, version: u32) -> PathBuf {
+    let mut file_name = config_path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    file_name.push(format!(".v{}.bak", version));
+    config_path.with_file_name(file_name)
+}
+
+/// The contents to back up before upgrading a config. Configs from before version 2 held the
+/// launch arguments, so their authentication tickets are taken out of the copy.
+fn get_backup_contents(contents: &str, document: &Map<String, Value>) -> String {
+    let arguments = match document.get("arguments") {
+        Some(Value::Array(arguments)) => arguments,
+        _ => return String::from(contents),
+    };
+    let redacted: Vec<Value> = arguments.iter().map(|argument| match argument {
+        Value::String(argument) => Value::String(LaunchArgument::new(argument.as_str()).to_redacted_string()),
+        other => other.clone(),
+    }).collect();
+    if redacted == *arguments {
+        return String::from(contents);
+    }
+    let mut document = document.clone();
+    document.insert(String::from("arguments"), Value::Array(redacted));
+    Value::Object(document).to_string()
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config {
+            schema_version: CONFIG_SCHEMA_VERSION,
+            steam_gameid: String::new(),
+            debug: false,
+            launch_request_ttl_secs: default_launch_request_ttl_secs(),
+            steam_launch: SteamLaunchStrategy::default(),
+            handoff_timeout_secs: default_handoff_timeout_secs(),
+            bounce_through_steam: false,
+            allow_unrecognized_arguments: false,
+            supervise: false,
+            update_check: UpdateCheckConfig::default(),
+        }
+    }
+    /// Reads the config at `path`, upgrading it to `CONFIG_SCHEMA_VERSION` first if it is older.
+    /// Upgraded configs are written back to `path` under its lock, and the original is kept next
+    /// to it.
+    pub fn from_path(path: &PathBuf) -> Result<Config, ConfigReadError> {
+        let (_, document, version) = Config::read_document(path)?;
+        if version == CONFIG_SCHEMA_VERSION {
+            return Config::from_document(document, path);
+        }
+        let _lock = Config::lock(path)?;
+        Config::read_upgrading(path)
+    }
+    /// Locks the config at `path` and reads it. Hold on to the returned lock until any changes
+    /// have been written back with `write_to_path`.
+    pub fn from_path_locked(path: &PathBuf) -> Result<(Config, FileLock), ConfigReadError> {
+        let lock = Config::lock(path)?;
+        let config = Config::read_upgrading(path)?;
+        Ok((config, lock))
+    }
+    fn lock(path: &Path) -> Result<FileLock, ConfigReadError> {
+        FileLock::acquire(path).context("lock config file", path).map_err(ConfigReadError::NotLockable)
+    }
+    /// Reads the config at `path`, returning its contents, the parsed document and its version.
+    fn read_document(path: &Path) -> Result<(String, Map<String, Value>, u32), ConfigReadError> {
+        let mut read_file = OpenOptions::new().read(true).write(false).open(path).context("open config file", path).map_err(ConfigReadError::NotOpenable)?;
+        let mut contents = String::new();
+        read_file.read_to_string(&mut contents).context("read config file", path).map_err(ConfigReadError::NotReadable)?;
+        let document = match serde_json::from_str(&contents) {
+            Ok(Value::Object(document)) => document,
+            Ok(_) => return Err(ConfigReadError::Malformed(Error::new("parse config file").with_path(path).with_source("expected a JSON object"))),
+            Err(err) => return Err(ConfigReadError::Malformed(Error::new("parse config file").with_path(path).with_source(err))),
+        };
+        let version = Config::get_document_version(&document, path)?;
+        if version > CONFIG_SCHEMA_VERSION {
+            return Err(ConfigReadError::TooNew(version));
+        }
+        Ok((contents, document, version))
+    }
+    /// Reads the config at `path` and upgrades it if it is older. The caller must hold the
+    /// config's lock, since the upgrade is written back.
+    fn read_upgrading(path: &PathBuf) -> Result<Config, ConfigReadError> {
+        // Another process may have upgraded it before we got the lock, so it is read again here.
+        let (contents, mut document, version) = Config::read_document(path)?;
+        if version == CONFIG_SCHEMA_VERSION {
+            return Config::from_document(document, path);
+        }
+        write_file_atomically(&get_config_backup_path(path, version), get_backup_contents(&contents, &document).as_bytes()).context("back up config file", path).map_err(ConfigReadError::NotMigratable)?;
+        Config::migrate(&mut document, version);
+        let config = Config::from_document(document, path)?;
+        config.write_to_path(path).context("upgrade config file", path).map_err(ConfigReadError::NotMigratable)?;
+        Ok(config)
+    }
+    fn get_document_version(document: &Map<String, Value>, path: &Path) -> Result<u32, ConfigReadError> {
+        let invalid = |value: &dyn fmt::Display| ConfigReadError::Malformed(Error::new("parse config file").with_path(path).with_source(format!("invalid schema_version {}", value)));
+        match document.get("schema_version") {
+            None => Ok(0),
+            Some(Value::Number(number)) => match number.as_u64() {
+                Some(version) if version <= u32::MAX as u64 => Ok(version as u32),
+                _ => Err(invalid(number)),
+            },
+            Some(other) => Err(invalid(other)),
+        }
+    }
+    /// Runs every migration step from `version` up to `CONFIG_SCHEMA_VERSION` on `document`.
+    fn migrate(document: &mut Map<String, Value>, version: u32) {
+        for step in version..CONFIG_SCHEMA_VERSION {
+            MIGRATIONS[step as usize](document);
+            document.insert(String::from("schema_version"), Value::from(step + 1));
+        }
+    }
+    fn from_document(document: Map<String, Value>, path: &Path) -> Result<Config, ConfigReadError> {
+        serde_json::from_value(Value::Object(document)).context("parse config file", path).map_err(ConfigReadError::Malformed)
+    }
+    /// Writes the config to a temporary file and then moves it over `path`, so readers never see a
+    /// partially written config.
+    pub fn write_to_path(&self, path: &PathBuf) -> Result<(), ConfigWriteError> {
+        let config_as_str = serde_json::to_string(self).context("serialize config for", path).map_err(ConfigWriteError::NotSerializable)?;
+        write_file_atomically(path, config_as_str.as_bytes()).context("write config file", path).map_err(ConfigWriteError::NotWriteable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use error::describe;
+    use std::fs;
+
+    fn read(path: &PathBuf) -> Config {
+        match Config::from_path(path) {
+            Ok(config) => config,
+            Err(err) => panic!("{}", describe(&err)),
+        }
+    }
+
+    fn test_path(name: &str) -> PathBuf {
+        let mut path = std::env::temp_dir();
+        path.push(format!("roblox_steam_launcher_config_test_{}_{}", std::process::id(), name));
+        fs::create_dir_all(&path).unwrap();
+        path.push("roblox_steam_launcher_config.json");
+        path
+    }
+
+    #[test]
+    fn migrates_unversioned_config() {
+        let path = test_path("unversioned");
+        let original = r#"{"steam_gameid":"123","debug":true,"arguments":["a"]}"#;
+        fs::write(&path, original).unwrap();
+
+        let config = read(&path);
+        assert_eq!(config.schema_version, CONFIG_SCHEMA_VERSION);
+        assert_eq!(config.steam_gameid, "123");
+        assert!(config.debug);
+        assert_eq!(config.launch_request_ttl_secs, 120);
+        assert_eq!(config.steam_launch, SteamLaunchStrategy::Url);
+        assert_eq!(config.handoff_timeout_secs, 60);
+        assert!(!config.bounce_through_steam);
+        assert!(!config.allow_unrecognized_arguments);
+        assert!(!config.supervise);
+        assert_eq!(config.update_check, UpdateCheckConfig::default());
+
+        assert_eq!(fs::read_to_string(get_config_backup_path(&path, 0)).unwrap(), original);
+        assert_eq!(read(&path), config);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn migrates_old_default_update_host() {
+        let path = test_path("update_host");
+        let document = |base_url: &str| format!(r#"{{"schema_version":1,"steam_gameid":"","debug":false,"update_check":{{"format":"client_version","base_url":"{}","channel":"live","connect_timeout_secs":5,"read_timeout_secs":10}}}}"#, base_url);
+
+        fs::write(&path, document("http://setup.roblox.com")).unwrap();
+        assert_eq!(read(&path).update_check.base_url, "");
+        fs::write(&path, document("http://localhost:8080")).unwrap();
+        assert_eq!(read(&path).update_check.base_url, "http://localhost:8080");
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn upgrades_under_lock() {
+        let path = test_path("locked");
+        let original = r#"{"steam_gameid":"123","debug":false}"#;
+        fs::write(&path, original).unwrap();
+
+        let lock = FileLock::acquire(&path).unwrap();
+        let reader_path = path.clone();
+        let reader = std::thread::spawn(move || read(&reader_path));
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+        drop(lock);
+        assert_eq!(reader.join().unwrap().schema_version, CONFIG_SCHEMA_VERSION);
+        assert!(fs::read_to_string(&path).unwrap().contains("schema_version"));
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn backups_leave_out_auth_tickets() {
+        let path = test_path("ticket");
+        fs::write(&path, r#"{"steam_gameid":"123","debug":false,"arguments":["roblox-player:1+launchmode:play+gameinfo:SECRETTICKET"]}"#).unwrap();
+
+        read(&path);
+        let backup = fs::read_to_string(get_config_backup_path(&path, 0)).unwrap();
+        assert!(!backup.contains("SECRETTICKET"));
+        assert!(backup.contains("launchmode:play"));
+        assert!(!fs::read_to_string(&path).unwrap().contains("SECRETTICKET"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+            assert_eq!(fs::metadata(get_config_backup_path(&path, 0)).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn rejects_newer_config() {
+        let path = test_path("newer");
+        fs::write(&path, format!(r#"{{"schema_version":{},"steam_gameid":"","debug":false}}"#, CONFIG_SCHEMA_VERSION + 1)).unwrap();
+
+        match Config::from_path(&path) {
+            Err(ConfigReadError::TooNew(version)) => assert_eq!(version, CONFIG_SCHEMA_VERSION + 1),
+            _ => panic!("expected ConfigReadError::TooNew"),
+        }
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn write_replaces_config_atomically() {
+        let path = test_path("atomic");
+        fs::write(&path, "{}").unwrap();
+
+        let mut config = Config::new();
+        config.steam_gameid = String::from("456");
+        match config.write_to_path(&path) {
+            Ok(_) => (),
+            Err(err) => panic!("{}", describe(&err)),
+        }
+        assert_eq!(read(&path), config);
+
+        let entries: Vec<_> = fs::read_dir(path.parent().unwrap()).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(entries, vec![path.file_name().unwrap().to_os_string()]);
+
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
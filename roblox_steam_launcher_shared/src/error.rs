@@ -0,0 +1,131 @@
+//! An error that records what was being done, to which file, and why it failed.
+//!
+//! The other error types in this crate implement `std::error::Error`, so any of them can be the
+//! source of an `Error`, and `describe` can print any of them with its whole chain of causes.
+//! An `Error` displays its whole chain itself, so it reads the same however it is printed. The
+//! other errors display only their own message and leave their causes to `describe`.
+
+use serde_json;
+
+use std::borrow::Cow;
+use std::error::Error as StdError;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub type BoxedError = Box<dyn StdError + Send + Sync + 'static>;
+
+#[derive(Debug)]
+pub struct Error {
+    operation: Cow<'static, str>,
+    path: Option<PathBuf>,
+    source: Option<BoxedError>,
+}
+
+impl Error {
+    /// An error that happened while trying to `operation`, such as "read" or "parse".
+    pub fn new<O: Into<Cow<'static, str>>>(operation: O) -> Error {
+        Error {
+            operation: operation.into(),
+            path: None,
+            source: None,
+        }
+    }
+
+    pub fn with_path(mut self, path: &Path) -> Error {
+        self.path = Some(path.to_path_buf());
+        self
+    }
+
+    pub fn with_source<E: Into<BoxedError>>(mut self, source: E) -> Error {
+        self.source = Some(source.into());
+        self
+    }
+
+    pub fn operation(&self) -> &str {
+        &self.operation
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+}
+
+/// Formats `error` followed by each of its causes, skipping causes its message already includes.
+pub fn describe(error: &dyn StdError) -> String {
+    let mut description = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        let cause_description = cause.to_string();
+        if !description.contains(&cause_description) {
+            description.push_str(": ");
+            description.push_str(&cause_description);
+        }
+        source = cause.source();
+    }
+    description
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "Cannot {}", self.operation)?;
+        if let Some(ref path) = self.path {
+            write!(formatter, " {}", path.display())?;
+        }
+        if let Some(ref source) = self.source {
+            write!(formatter, ": {}", describe(&**source))?;
+        }
+        Ok(())
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self.source {
+            Some(ref source) => Some(&**source),
+            None => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::new("access a file").with_source(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Error {
+        Error::new("handle JSON").with_source(err)
+    }
+}
+
+/// Attaches what was being done, and to which file, to an error.
+pub trait Context<T> {
+    fn context<O: Into<Cow<'static, str>>>(self, operation: O, path: &Path) -> Result<T, Error>;
+}
+
+impl<T, E: Into<BoxedError>> Context<T> for Result<T, E> {
+    fn context<O: Into<Cow<'static, str>>>(self, operation: O, path: &Path) -> Result<T, Error> {
+        self.map_err(|err| Error::new(operation).with_path(path).with_source(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn describes_the_whole_chain() {
+        let inner: Result<(), io::Error> = Err(io::Error::new(io::ErrorKind::NotFound, "no such file"));
+        let inner = inner.context("read", Path::new("config.json")).unwrap_err();
+        let outer = Error::new("start").with_source(inner);
+        assert_eq!(outer.to_string(), "Cannot start: Cannot read config.json: no such file");
+        assert_eq!(outer.source().unwrap().to_string(), "Cannot read config.json: no such file");
+        assert_eq!(describe(&outer), outer.to_string());
+
+        let parsed: Result<u32, serde_json::Error> = serde_json::from_str("nope");
+        let err: Error = parsed.unwrap_err().into();
+        assert!(describe(&err).starts_with("Cannot handle JSON: expected"));
+    }
+}
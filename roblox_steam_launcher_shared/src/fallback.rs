@@ -0,0 +1,76 @@
+//! Records launches that went straight to Roblox because the master never picked them up, so the
+//! reason can be reported the next time the master runs.
+
+use serde_json;
+
+use unix_time_now;
+use write_file_atomically;
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FallbackRecord {
+    pub request_id: String,
+    /// When Roblox was launched directly, in seconds since the Unix epoch.
+    pub time: u64,
+    /// Why the launch through Steam failed.
+    pub reason: String,
+}
+
+pub fn get_fallback_file_name() -> &'static str {
+    "roblox_steam_launcher_fallback.json"
+}
+
+fn get_fallback_path(directory: &Path) -> PathBuf {
+    directory.join(get_fallback_file_name())
+}
+
+fn invalid_data(err: serde_json::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+impl FallbackRecord {
+    pub fn new(request_id: &str, reason: String) -> FallbackRecord {
+        FallbackRecord {
+            request_id: String::from(request_id),
+            time: unix_time_now(),
+            reason,
+        }
+    }
+
+    /// Saves the record in `directory`, replacing any earlier one.
+    pub fn write_to(&self, directory: &Path) -> io::Result<()> {
+        let contents = serde_json::to_string(self).map_err(invalid_data)?;
+        write_file_atomically(&get_fallback_path(directory), contents.as_bytes())
+    }
+
+    /// Reads and removes the record saved in `directory`, if there is one.
+    pub fn take(directory: &Path) -> io::Result<Option<FallbackRecord>> {
+        let path = get_fallback_path(directory);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        fs::remove_file(&path)?;
+        serde_json::from_str(&contents).map(Some).map_err(invalid_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_is_taken_once() {
+        let dir = std::env::temp_dir().join(format!("roblox_steam_launcher_fallback_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let record = FallbackRecord::new("abc", String::from("Steam is not running"));
+        record.write_to(&dir).unwrap();
+        assert_eq!(FallbackRecord::take(&dir).unwrap(), Some(record));
+        assert_eq!(FallbackRecord::take(&dir).unwrap(), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
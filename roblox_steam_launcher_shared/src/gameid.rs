@@ -0,0 +1,84 @@
+use crc::crc32;
+use std::path::Path;
+
+// Steam identifies a non-Steam shortcut by a CRC32 of its quoted `exe` value followed by its
+// `AppName`, using the standard reflected CRC32 (poly 0x04C11DB7, xor in/out 0xFFFFFFFF) over
+// the UTF-8 bytes. The top bit is always set, and the 64-bit form used by `steam://rungameid/`
+// has 0x02000000 in the low 32 bits.
+//
+// Originally from https://github.com/scottrice/Ice/blob/7130b54c8d2fa7d0e2c0994ca1f2aa3fb2a27ba9/ice/steam_grid.py
+
+/// A Steam shortcut id, from which every form Steam uses can be derived.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ShortcutId {
+    crc: u32,
+}
+
+impl ShortcutId {
+    /// Calculates the id from the raw `exe` value (including quotes) and the shortcut name.
+    pub fn from_raw(exe: &str, name: &str) -> ShortcutId {
+        let mut input = Vec::with_capacity(exe.len() + name.len());
+        input.extend_from_slice(exe.as_bytes());
+        input.extend_from_slice(name.as_bytes());
+        ShortcutId {
+            crc: crc32::checksum_ieee(&input),
+        }
+    }
+
+    /// Calculates the id for a shortcut to `target`, quoting the path the same way the installer
+    /// writes it to `shortcuts.vdf`.
+    pub fn new(target: &Path, name: &str) -> ShortcutId {
+        let exe = format!("\"{}\"", target.as_os_str().to_string_lossy());
+        ShortcutId::from_raw(&exe, name)
+    }
+
+    /// The 64-bit id used in `steam://rungameid/` URLs.
+    pub fn rungameid(&self) -> u64 {
+        ((self.grid_id() as u64) << 32) | 0x02000000
+    }
+
+    /// The 32-bit `appid` stored in `shortcuts.vdf`. Steam stores this as a signed integer.
+    pub fn appid(&self) -> i32 {
+        self.grid_id() as i32
+    }
+
+    /// The 32-bit id used to name custom artwork in `userdata/<user>/config/grid`.
+    pub fn grid_id(&self) -> u32 {
+        self.crc | 0x80000000
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShortcutId;
+    use std::path::Path;
+
+    // Vectors produced by the old `python_gameid_crc/gameid.py` helper.
+
+    #[test]
+    fn matches_python_helper() {
+        let id = ShortcutId::new(Path::new("C:\\Program Files (x86)\\Roblox\\Versions\\roblox_steam_launcher_master.exe"), "Roblox");
+        assert_eq!(id.rungameid(), 18431139345160208384);
+        assert_eq!(id.grid_id(), 4291334037);
+        assert_eq!(id.appid(), -3633259);
+    }
+
+    #[test]
+    fn handles_non_ascii_paths() {
+        let id = ShortcutId::new(Path::new("C:\\Users\\Jörg\\AppData\\Local\\Roblox\\Versions\\roblox_steam_launcher_master.exe"), "Roblox");
+        assert_eq!(id.rungameid(), 12008777805744570368);
+        assert_eq!(id.grid_id(), 2796011466);
+        assert_eq!(id.appid(), -1498955830);
+
+        let id = ShortcutId::new(Path::new("C:\\Users\\山田\\AppData\\Local\\Roblox\\Versions\\roblox_steam_launcher_master.exe"), "Roblox");
+        assert_eq!(id.rungameid(), 17842701926228033536);
+        assert_eq!(id.grid_id(), 4154327774);
+        assert_eq!(id.appid(), -140639522);
+    }
+
+    #[test]
+    fn raw_input() {
+        assert_eq!(ShortcutId::from_raw("", "").rungameid(), 9223372036888330240);
+        assert_eq!(ShortcutId::from_raw("1234", "56789").rungameid(), 14696434319421865984);
+    }
+}
@@ -0,0 +1,501 @@
+//! Whether the substitute is installed in a Roblox version directory, and putting it there or
+//! taking it out again.
+//!
+//! The substitute is recognised by `SUBSTITUTE_MARKER`, which it embeds, rather than by its file
+//! name, so a version directory can be inspected without trusting how its files are named.
+
+use error::{describe, Error};
+use journal::{Journal, Operation, Step};
+use versions::{discover_versions, VersionOrder, VersionsError, LAUNCHER_FILE_NAME, ORIGINAL_LAUNCHER_FILE_NAME};
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Embedded in the substitute executable so it can be told apart from Roblox's launcher.
+pub const SUBSTITUTE_MARKER: &[u8] = b"roblox_steam_launcher_substitute_marker\0";
+
+/// The version of the substitute built from this tree. Bump it, and the number in
+/// `SUBSTITUTE_VERSION_TAG`, whenever the substitute's behaviour changes, so `apply` replaces
+/// copies of older builds.
+pub const SUBSTITUTE_VERSION: u32 = 2;
+
+const SUBSTITUTE_VERSION_PREFIX: &[u8] = b"roblox_steam_launcher_substitute_version=";
+
+/// Embedded in the substitute next to `SUBSTITUTE_MARKER`, recording `SUBSTITUTE_VERSION`.
+pub const SUBSTITUTE_VERSION_TAG: &[u8] = b"roblox_steam_launcher_substitute_version=2\0";
+
+/// Substitutes from before `SUBSTITUTE_VERSION_TAG` was embedded carry only the marker.
+const UNTAGGED_SUBSTITUTE_VERSION: u32 = 1;
+
+pub const SUBSTITUTE_FILE_NAME: &str = "roblox_steam_launcher_substitute.exe";
+
+/// Where the substitute is copied in a version directory before it is moved into place.
+const STAGED_SUBSTITUTE_FILE_NAME: &str = "roblox_steam_launcher_substitute.exe.new";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExecutableKind {
+    Missing,
+    Substitute,
+    /// Anything that isn't the substitute, which in a version directory is Roblox's launcher.
+    Roblox,
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// Returns the version of the substitute at `path`, or `None` if it isn't the substitute.
+pub fn substitute_version(path: &Path) -> io::Result<Option<u32>> {
+    let contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    if find(&contents, SUBSTITUTE_MARKER).is_none() {
+        return Ok(None);
+    }
+    let start = match find(&contents, SUBSTITUTE_VERSION_PREFIX) {
+        Some(position) => position + SUBSTITUTE_VERSION_PREFIX.len(),
+        None => return Ok(Some(UNTAGGED_SUBSTITUTE_VERSION)),
+    };
+    let digits = contents[start..].iter().take_while(|byte| byte.is_ascii_digit()).count();
+    match std::str::from_utf8(&contents[start..start + digits]).ok().and_then(|digits| digits.parse().ok()) {
+        Some(version) => Ok(Some(version)),
+        None => Err(io::Error::new(io::ErrorKind::InvalidData, "the substitute's version is unreadable")),
+    }
+}
+
+impl ExecutableKind {
+    /// Identifies the executable at `path` by looking for `SUBSTITUTE_MARKER` in it.
+    pub fn of(path: &Path) -> io::Result<ExecutableKind> {
+        let contents = match fs::read(path) {
+            Ok(contents) => contents,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(ExecutableKind::Missing),
+            Err(err) => return Err(err),
+        };
+        if find(&contents, SUBSTITUTE_MARKER).is_some() {
+            Ok(ExecutableKind::Substitute)
+        } else {
+            Ok(ExecutableKind::Roblox)
+        }
+    }
+}
+
+/// The state of one version directory.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallState {
+    /// Only Roblox's launcher is there.
+    Pristine,
+    /// The substitute is in place of Roblox's launcher, which is saved next to it.
+    Applied,
+    /// Roblox's launcher is in place, and an identical copy of it is saved next to it, such as
+    /// after Roblox put its launcher back. The copy can be deleted without losing anything.
+    Stale,
+    /// None of the above. Nothing is moved until this is fixed by hand, since doing so could lose
+    /// Roblox's launcher.
+    Broken(String),
+}
+
+impl fmt::Display for InstallState {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InstallState::Pristine => write!(formatter, "pristine"),
+            InstallState::Applied => write!(formatter, "applied"),
+            InstallState::Stale => write!(formatter, "stale"),
+            InstallState::Broken(reason) => write!(formatter, "broken ({})", reason),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum InstallError {
+    /// The version directory is not in a state the operation can start from.
+    Inconsistent { path: PathBuf, state: InstallState },
+    /// What should be the substitute doesn't carry its marker.
+    NotSubstitute(PathBuf),
+    Io(Error),
+}
+
+impl fmt::Display for InstallError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InstallError::Inconsistent { path, state } => write!(formatter, "Roblox version directory {} is {}", path.display(), state),
+            InstallError::NotSubstitute(path) => write!(formatter, "{} is not the Roblox Steam Launcher substitute", path.display()),
+            InstallError::Io(_) => write!(formatter, "Roblox's files could not be changed"),
+        }
+    }
+}
+
+impl std::error::Error for InstallError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InstallError::Io(err) => Some(err),
+            InstallError::Inconsistent { .. } | InstallError::NotSubstitute(_) => None,
+        }
+    }
+}
+
+pub(crate) fn io_error(operation: &'static str, path: &Path) -> impl FnOnce(io::Error) -> InstallError {
+    let path = path.to_path_buf();
+    move |error| InstallError::Io(Error::new(operation).with_path(&path).with_source(error))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApplyOutcome {
+    Applied,
+    /// An older build of the substitute was replaced with the newer one.
+    Updated { from: u32, to: u32 },
+    AlreadyApplied,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnapplyOutcome {
+    Unapplied,
+    AlreadyPristine,
+}
+
+impl InstallState {
+    /// Inspects the launcher and saved launcher in `version_path`.
+    pub fn of(version_path: &Path) -> Result<InstallState, InstallError> {
+        let launcher_path = version_path.join(LAUNCHER_FILE_NAME);
+        let original_path = version_path.join(ORIGINAL_LAUNCHER_FILE_NAME);
+        let launcher = ExecutableKind::of(&launcher_path).map_err(io_error("inspect", &launcher_path))?;
+        let original = ExecutableKind::of(&original_path).map_err(io_error("inspect", &original_path))?;
+        Ok(match (launcher, original) {
+            (ExecutableKind::Roblox, ExecutableKind::Missing) => InstallState::Pristine,
+            (ExecutableKind::Substitute, ExecutableKind::Roblox) => InstallState::Applied,
+            // After an update both may be genuine launchers, and there's no telling which is current.
+            (ExecutableKind::Roblox, ExecutableKind::Roblox) => if same_contents(&launcher_path, &original_path)? {
+                InstallState::Stale
+            } else {
+                InstallState::Broken(String::from("the launcher and the saved original are different Roblox launchers"))
+            },
+            (ExecutableKind::Missing, ExecutableKind::Missing) => InstallState::Broken(String::from("there is no launcher")),
+            (ExecutableKind::Missing, ExecutableKind::Roblox) => InstallState::Broken(String::from("the launcher is missing, but the saved original is there")),
+            (ExecutableKind::Substitute, ExecutableKind::Missing) => InstallState::Broken(String::from("the substitute is there, but the original launcher is missing")),
+            (_, ExecutableKind::Substitute) => InstallState::Broken(String::from("the saved original launcher is a substitute")),
+        })
+    }
+}
+
+fn same_contents(first: &Path, second: &Path) -> Result<bool, InstallError> {
+    let first_contents = fs::read(first).map_err(io_error("read", first))?;
+    let second_contents = fs::read(second).map_err(io_error("read", second))?;
+    Ok(first_contents == second_contents)
+}
+
+/// Deletes the saved launcher in a `Stale` directory, leaving it pristine. Being a copy of the
+/// launcher, nothing is lost if this is interrupted, so it isn't journaled.
+fn remove_redundant_original(version_path: &Path) -> Result<(), InstallError> {
+    let original_path = version_path.join(ORIGINAL_LAUNCHER_FILE_NAME);
+    fs::remove_file(&original_path).map_err(io_error("remove", &original_path))
+}
+
+/// Puts the substitute at `substitute_path` in place of the launcher in `version_path`, saving
+/// the launcher next to it. An older build of the substitute already there is replaced. The
+/// launcher is only moved once the substitute has been copied next to it, and the steps are
+/// journaled in the parent directory so `journal::recover` can deal with an interruption.
+pub fn apply(version_path: &Path, substitute_path: &Path) -> Result<ApplyOutcome, InstallError> {
+    let version = match substitute_version(substitute_path).map_err(io_error("inspect", substitute_path))? {
+        Some(version) => version,
+        None => return Err(InstallError::NotSubstitute(substitute_path.to_path_buf())),
+    };
+    let launcher_path = version_path.join(LAUNCHER_FILE_NAME);
+    let original_path = version_path.join(ORIGINAL_LAUNCHER_FILE_NAME);
+    let staged_path = version_path.join(STAGED_SUBSTITUTE_FILE_NAME);
+    let stage = Step::Copy { from: substitute_path.to_path_buf(), to: staged_path.clone() };
+    let mut state = InstallState::of(version_path)?;
+    if state == InstallState::Stale {
+        remove_redundant_original(version_path)?;
+        state = InstallState::Pristine;
+    }
+    let (steps, outcome) = match state {
+        InstallState::Applied => match substitute_version(&launcher_path).map_err(io_error("inspect", &launcher_path))? {
+            Some(applied) if applied < version => (vec![
+                stage,
+                Step::Replace { from: staged_path, to: launcher_path },
+            ], ApplyOutcome::Updated { from: applied, to: version }),
+            _ => return Ok(ApplyOutcome::AlreadyApplied),
+        },
+        InstallState::Pristine => (vec![
+            stage,
+            Step::Rename { from: launcher_path.clone(), to: original_path },
+            Step::Rename { from: staged_path, to: launcher_path },
+        ], ApplyOutcome::Applied),
+        state => return Err(InstallError::Inconsistent { path: version_path.to_path_buf(), state }),
+    };
+    Journal::new(Operation::Apply, version_path, steps).run(journal_directory(version_path))?;
+    Ok(outcome)
+}
+
+/// Puts the saved launcher in `version_path` back in place of the substitute.
+pub fn unapply(version_path: &Path) -> Result<UnapplyOutcome, InstallError> {
+    let launcher_path = version_path.join(LAUNCHER_FILE_NAME);
+    let original_path = version_path.join(ORIGINAL_LAUNCHER_FILE_NAME);
+    match InstallState::of(version_path)? {
+        InstallState::Pristine => Ok(UnapplyOutcome::AlreadyPristine),
+        InstallState::Stale => {
+            remove_redundant_original(version_path)?;
+            Ok(UnapplyOutcome::Unapplied)
+        },
+        InstallState::Applied => {
+            let steps = vec![Step::Replace { from: original_path, to: launcher_path }];
+            Journal::new(Operation::Unapply, version_path, steps).run(journal_directory(version_path))?;
+            Ok(UnapplyOutcome::Unapplied)
+        },
+        state => Err(InstallError::Inconsistent { path: version_path.to_path_buf(), state }),
+    }
+}
+
+/// What `repair` did to one version directory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RepairAction {
+    Applied(ApplyOutcome),
+    Unapplied(UnapplyOutcome),
+}
+
+impl RepairAction {
+    /// Whether anything in the directory was changed.
+    pub fn changed(self) -> bool {
+        self != RepairAction::Applied(ApplyOutcome::AlreadyApplied) && self != RepairAction::Unapplied(UnapplyOutcome::AlreadyPristine)
+    }
+}
+
+impl fmt::Display for RepairAction {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RepairAction::Applied(ApplyOutcome::Applied) => write!(formatter, "applied the launcher"),
+            RepairAction::Applied(ApplyOutcome::Updated { from, to }) => write!(formatter, "updated the substitute from version {} to {}", from, to),
+            RepairAction::Applied(ApplyOutcome::AlreadyApplied) => write!(formatter, "left applied"),
+            RepairAction::Unapplied(UnapplyOutcome::Unapplied) => write!(formatter, "reverted to Roblox's launcher"),
+            RepairAction::Unapplied(UnapplyOutcome::AlreadyPristine) => write!(formatter, "left pristine"),
+        }
+    }
+}
+
+/// What `repair` found in one version directory, and what it did about it.
+#[derive(Debug)]
+pub struct RepairReport {
+    pub path: PathBuf,
+    /// Whether this is the newest version, which is the one the launcher is applied to.
+    pub current: bool,
+    /// The state before repairing, if it could be inspected.
+    pub state: Option<InstallState>,
+    pub result: Result<RepairAction, InstallError>,
+}
+
+impl fmt::Display for RepairReport {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let name = self.path.file_name().unwrap_or_else(|| self.path.as_os_str()).to_string_lossy();
+        write!(formatter, "{}{}: ", name, if self.current { " (current)" } else { "" })?;
+        if let Err(InstallError::Inconsistent { ref state, .. }) = self.result {
+            return write!(formatter, "{}, left alone", state);
+        }
+        if let Some(ref state) = self.state {
+            write!(formatter, "was {}, ", state)?;
+        }
+        match self.result {
+            Ok(action) => write!(formatter, "{}", action),
+            Err(ref err) => write!(formatter, "not repaired: {}", describe(err)),
+        }
+    }
+}
+
+/// Applies the substitute to the newest player version under `versions_path` and reverts every
+/// other player version. Broken directories are reported and left alone, so this is safe to run
+/// at any time.
+pub fn repair(versions_path: &Path, substitute_path: &Path) -> Result<Vec<RepairReport>, VersionsError> {
+    let players = discover_versions(versions_path, VersionOrder::Created)?.into_iter().filter(|directory| directory.is_player());
+    Ok(players.enumerate().map(|(index, directory)| {
+        let current = index == 0;
+        let state = InstallState::of(&directory.path).ok();
+        let result = if current {
+            apply(&directory.path, substitute_path).map(RepairAction::Applied)
+        } else {
+            unapply(&directory.path).map(RepairAction::Unapplied)
+        };
+        RepairReport { path: directory.path, current, state, result }
+    }).collect())
+}
+
+/// The `Versions` directory, which holds the config and the journal.
+fn journal_directory(version_path: &Path) -> &Path {
+    version_path.parent().unwrap_or(version_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("roblox_steam_launcher_install_test_{}_{}", std::process::id(), name));
+        fs::create_dir_all(path.join("version-abc")).unwrap();
+        let mut substitute = b"substitute ".to_vec();
+        substitute.extend_from_slice(SUBSTITUTE_MARKER);
+        substitute.extend_from_slice(SUBSTITUTE_VERSION_TAG);
+        fs::write(path.join(SUBSTITUTE_FILE_NAME), substitute).unwrap();
+        fs::write(path.join("version-abc").join(LAUNCHER_FILE_NAME), "roblox").unwrap();
+        path
+    }
+
+    #[test]
+    fn applies_and_unapplies() {
+        let dir = test_dir("cycle");
+        let version = dir.join("version-abc");
+        let substitute = dir.join(SUBSTITUTE_FILE_NAME);
+        assert_eq!(InstallState::of(&version).unwrap(), InstallState::Pristine);
+        assert_eq!(unapply(&version).unwrap(), UnapplyOutcome::AlreadyPristine);
+
+        assert_eq!(apply(&version, &substitute).unwrap(), ApplyOutcome::Applied);
+        assert_eq!(InstallState::of(&version).unwrap(), InstallState::Applied);
+        assert_eq!(apply(&version, &substitute).unwrap(), ApplyOutcome::AlreadyApplied);
+
+        assert_eq!(unapply(&version).unwrap(), UnapplyOutcome::Unapplied);
+        assert_eq!(InstallState::of(&version).unwrap(), InstallState::Pristine);
+        assert_eq!(fs::read_to_string(version.join(LAUNCHER_FILE_NAME)).unwrap(), "roblox");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn removes_redundant_original() {
+        let dir = test_dir("stale");
+        let version = dir.join("version-abc");
+        fs::write(version.join(ORIGINAL_LAUNCHER_FILE_NAME), "roblox").unwrap();
+        assert_eq!(InstallState::of(&version).unwrap(), InstallState::Stale);
+        assert_eq!(unapply(&version).unwrap(), UnapplyOutcome::Unapplied);
+        assert_eq!(InstallState::of(&version).unwrap(), InstallState::Pristine);
+        assert_eq!(fs::read_to_string(version.join(LAUNCHER_FILE_NAME)).unwrap(), "roblox");
+
+        fs::write(version.join(ORIGINAL_LAUNCHER_FILE_NAME), "roblox").unwrap();
+        assert_eq!(apply(&version, &dir.join(SUBSTITUTE_FILE_NAME)).unwrap(), ApplyOutcome::Applied);
+        assert_eq!(InstallState::of(&version).unwrap(), InstallState::Applied);
+        assert_eq!(fs::read_to_string(version.join(ORIGINAL_LAUNCHER_FILE_NAME)).unwrap(), "roblox");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_differing_launchers() {
+        let dir = test_dir("differing");
+        let version = dir.join("version-abc");
+        fs::write(version.join(ORIGINAL_LAUNCHER_FILE_NAME), "older roblox").unwrap();
+        match InstallState::of(&version).unwrap() {
+            InstallState::Broken(_) => (),
+            other => panic!("expected a broken state, got {:?}", other),
+        }
+        match apply(&version, &dir.join(SUBSTITUTE_FILE_NAME)) {
+            Err(InstallError::Inconsistent { .. }) => (),
+            other => panic!("expected an inconsistent state, got {:?}", other),
+        }
+        match unapply(&version) {
+            Err(InstallError::Inconsistent { .. }) => (),
+            other => panic!("expected an inconsistent state, got {:?}", other),
+        }
+        assert_eq!(fs::read_to_string(version.join(LAUNCHER_FILE_NAME)).unwrap(), "roblox");
+        assert_eq!(fs::read_to_string(version.join(ORIGINAL_LAUNCHER_FILE_NAME)).unwrap(), "older roblox");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn updates_outdated_substitute() {
+        let dir = test_dir("outdated");
+        let version = dir.join("version-abc");
+        let substitute = dir.join(SUBSTITUTE_FILE_NAME);
+        assert_eq!(substitute_version(&substitute).unwrap(), Some(SUBSTITUTE_VERSION));
+        fs::rename(version.join(LAUNCHER_FILE_NAME), version.join(ORIGINAL_LAUNCHER_FILE_NAME)).unwrap();
+        fs::write(version.join(LAUNCHER_FILE_NAME), SUBSTITUTE_MARKER).unwrap();
+        assert_eq!(InstallState::of(&version).unwrap(), InstallState::Applied);
+        assert_eq!(substitute_version(&version.join(LAUNCHER_FILE_NAME)).unwrap(), Some(UNTAGGED_SUBSTITUTE_VERSION));
+
+        assert_eq!(apply(&version, &substitute).unwrap(), ApplyOutcome::Updated { from: UNTAGGED_SUBSTITUTE_VERSION, to: SUBSTITUTE_VERSION });
+        assert_eq!(substitute_version(&version.join(LAUNCHER_FILE_NAME)).unwrap(), Some(SUBSTITUTE_VERSION));
+        assert_eq!(apply(&version, &substitute).unwrap(), ApplyOutcome::AlreadyApplied);
+        assert_eq!(fs::read_to_string(version.join(ORIGINAL_LAUNCHER_FILE_NAME)).unwrap(), "roblox");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn substitute_version_matches_tag() {
+        assert_eq!(SUBSTITUTE_VERSION_TAG, format!("roblox_steam_launcher_substitute_version={}\0", SUBSTITUTE_VERSION).as_bytes());
+    }
+
+    #[test]
+    fn recovers_interrupted_apply() {
+        let dir = test_dir("interrupted");
+        let version = dir.join("version-abc");
+        let substitute = dir.join(SUBSTITUTE_FILE_NAME);
+        // Interrupted after saving the launcher, before the substitute was moved into place.
+        let mut journal = Journal::new(Operation::Apply, &version, vec![
+            Step::Copy { from: substitute.clone(), to: version.join(STAGED_SUBSTITUTE_FILE_NAME) },
+            Step::Rename { from: version.join(LAUNCHER_FILE_NAME), to: version.join(ORIGINAL_LAUNCHER_FILE_NAME) },
+            Step::Rename { from: version.join(STAGED_SUBSTITUTE_FILE_NAME), to: version.join(LAUNCHER_FILE_NAME) },
+        ]);
+        journal.completed = 1;
+        fs::write(dir.join(::journal::get_journal_file_name()), ::serde_json::to_string(&journal).unwrap()).unwrap();
+        fs::copy(&substitute, version.join(STAGED_SUBSTITUTE_FILE_NAME)).unwrap();
+        fs::rename(version.join(LAUNCHER_FILE_NAME), version.join(ORIGINAL_LAUNCHER_FILE_NAME)).unwrap();
+        assert!(InstallState::of(&version).unwrap() != InstallState::Applied);
+
+        assert_eq!(::journal::recover(&dir).unwrap(), Some(::journal::Recovery::RolledForward(Operation::Apply, version.clone())));
+        assert_eq!(InstallState::of(&version).unwrap(), InstallState::Applied);
+        assert!(!version.join(STAGED_SUBSTITUTE_FILE_NAME).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn repairs_every_version() {
+        let dir = test_dir("repair");
+        let substitute = dir.join(SUBSTITUTE_FILE_NAME);
+        // An older version still applied, and a newer one Roblox just installed.
+        apply(&dir.join("version-abc"), &substitute).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::create_dir_all(dir.join("version-def")).unwrap();
+        fs::write(dir.join("version-def").join(LAUNCHER_FILE_NAME), "roblox").unwrap();
+
+        let reports = repair(&dir, &substitute).unwrap();
+        assert_eq!(reports.len(), 2);
+        assert!(reports[0].current && reports[0].path.ends_with("version-def"));
+        assert_eq!(reports[0].result.as_ref().unwrap(), &RepairAction::Applied(ApplyOutcome::Applied));
+        assert_eq!(reports[1].state, Some(InstallState::Applied));
+        assert_eq!(reports[1].result.as_ref().unwrap(), &RepairAction::Unapplied(UnapplyOutcome::Unapplied));
+        assert_eq!(InstallState::of(&dir.join("version-abc")).unwrap(), InstallState::Pristine);
+
+        // Nothing left to do the second time.
+        assert!(repair(&dir, &substitute).unwrap().iter().all(|report| !report.result.as_ref().unwrap().changed()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn refuses_broken_directories() {
+        let dir = test_dir("broken");
+        let version = dir.join("version-abc");
+        let substitute = dir.join(SUBSTITUTE_FILE_NAME);
+        fs::copy(&substitute, version.join(LAUNCHER_FILE_NAME)).unwrap();
+        match InstallState::of(&version).unwrap() {
+            InstallState::Broken(_) => (),
+            other => panic!("expected a broken state, got {:?}", other),
+        }
+        match unapply(&version) {
+            Err(InstallError::Inconsistent { .. }) => (),
+            other => panic!("expected an inconsistent state, got {:?}", other),
+        }
+        match apply(&version, &substitute) {
+            Err(InstallError::Inconsistent { .. }) => (),
+            other => panic!("expected an inconsistent state, got {:?}", other),
+        }
+        assert!(version.join(LAUNCHER_FILE_NAME).is_file());
+
+        // Only the real substitute may be applied.
+        fs::write(version.join(LAUNCHER_FILE_NAME), "roblox").unwrap();
+        match apply(&version, &version.join(LAUNCHER_FILE_NAME)) {
+            Err(InstallError::NotSubstitute(_)) => (),
+            other => panic!("expected the substitute to be rejected, got {:?}", other),
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
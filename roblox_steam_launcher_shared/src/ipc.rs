@@ -0,0 +1,719 @@
+//! Hands launch requests from the substitute to the master.
+//!
+//! The substitute starts a `HandoffServer` before asking Steam to start the master, and queues a
+//! description of how to reach it in a handoff file next to the config. The master reads that
+//! queue when it starts, drops expired and superseded requests, connects to the newest one,
+//! receives the request and acknowledges it. Dropped requests are noted in the queue, so their
+//! substitutes know not to launch them either. If no socket can be bound, the request is stored in
+//! the queue itself and the master acknowledges it by marking it in the queue.
+
+use serde_json;
+
+use error::{describe, Error};
+use launch_uri::LaunchArgument;
+use lock::FileLock;
+use unix_time_now;
+use write_file_atomically;
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::io::prelude::*;
+use std::io::BufReader;
+use std::net::{SocketAddr, TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const ACK_MESSAGE: &str = "ack";
+
+/// How long the substitute waits for the master to pick up a launch request unless the config says
+/// otherwise.
+pub const HANDOFF_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long the master waits for the substitute to answer once it has found a queued request.
+pub const HANDOFF_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The version of the handoff protocol this build speaks. Bump it whenever the handoff changes in
+/// a way an older master could misread.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// The oldest protocol version the master still understands. Requests from before the version was
+/// stamped are version 1, which only differs in lacking it.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+fn unstamped_protocol_version() -> u32 {
+    1
+}
+
+fn generate_id() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    format!("{:016x}", hasher.finish())
+}
+
+#[derive(Clone,Debug,PartialEq,Serialize, Deserialize)]
+pub struct LaunchRequest {
+    /// A random id, which also proves to the substitute that the master read the handoff queue.
+    pub id: String,
+    /// When the request was made, in seconds since the Unix epoch.
+    pub created: u64,
+    pub arguments: Vec<LaunchArgument>,
+    /// The protocol version of the substitute that made the request.
+    #[serde(default = "unstamped_protocol_version")]
+    pub protocol: u32,
+}
+
+impl LaunchRequest {
+    pub fn new(arguments: Vec<String>) -> LaunchRequest {
+        LaunchRequest {
+            id: generate_id(),
+            created: unix_time_now(),
+            arguments: arguments.into_iter().map(LaunchArgument::from).collect(),
+            protocol: PROTOCOL_VERSION,
+        }
+    }
+
+    /// How many seconds ago the request was made.
+    pub fn age_secs(&self) -> u64 {
+        unix_time_now().saturating_sub(self.created)
+    }
+}
+
+#[derive(Clone,Debug,PartialEq,Serialize, Deserialize)]
+#[serde(tag = "transport", rename_all = "snake_case")]
+enum HandoffTransport {
+    Unix { path: String },
+    Tcp { address: String },
+    /// Holds the authentication ticket on disk, so it is taken out of the queue as soon as the
+    /// master reads it.
+    File { arguments: Vec<LaunchArgument> },
+}
+
+#[derive(Clone,Debug,PartialEq,Serialize, Deserialize)]
+struct QueuedHandoff {
+    id: String,
+    created: u64,
+    #[serde(default = "unstamped_protocol_version")]
+    protocol: u32,
+    transport: HandoffTransport,
+}
+
+#[derive(Clone,Debug,Default,PartialEq,Serialize, Deserialize)]
+struct HandoffQueue {
+    #[serde(default)]
+    pending: Vec<QueuedHandoff>,
+    /// Ids of file handoffs the master has taken, for their substitutes to see.
+    #[serde(default)]
+    acknowledged: Vec<String>,
+    /// Requests the master dropped on purpose, for their substitutes to see.
+    #[serde(default)]
+    discarded: Vec<DiscardedHandoff>,
+}
+
+#[derive(Clone,Debug,PartialEq,Serialize, Deserialize)]
+struct DiscardedHandoff {
+    id: String,
+    reason: String,
+}
+
+/// What became of a request its substitute took back out of the queue.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Withdrawal {
+    /// It was still queued, so the master will never see it and the substitute may launch it.
+    Withdrawn,
+    /// The master acknowledged a file handoff in the meantime and is launching it.
+    Acknowledged,
+    /// The master dropped it on purpose, for the given reason, so it must not be launched at all.
+    Discarded(String),
+    /// The master took it out of the queue without acknowledging or dropping it, so it may still
+    /// be connecting, or may have failed to.
+    Claimed,
+}
+
+#[derive(Debug)]
+pub enum HandoffError {
+    Io(io::Error),
+    Malformed(Error),
+    /// The other side answered with something other than what the protocol expects.
+    Rejected(String),
+    TimedOut,
+}
+
+impl fmt::Display for HandoffError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HandoffError::Io(_) => write!(formatter, "Launch handoff failed"),
+            HandoffError::Malformed(_) => write!(formatter, "Malformed launch handoff"),
+            HandoffError::Rejected(err) => write!(formatter, "Launch handoff was rejected: {}", err),
+            HandoffError::TimedOut => write!(formatter, "Timed out waiting for the launch handoff"),
+        }
+    }
+}
+
+impl std::error::Error for HandoffError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HandoffError::Io(err) => Some(err),
+            HandoffError::Malformed(err) => Some(err),
+            HandoffError::Rejected(_) | HandoffError::TimedOut => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for HandoffError {
+    fn from(err: serde_json::Error) -> HandoffError {
+        HandoffError::Malformed(Error::from(err))
+    }
+}
+
+impl From<io::Error> for HandoffError {
+    fn from(err: io::Error) -> HandoffError {
+        HandoffError::Io(err)
+    }
+}
+
+/// Why the master dropped a queued launch request instead of launching it.
+#[derive(Debug)]
+pub enum DropReason {
+    /// The request was older than the configured time to live.
+    Expired { age_secs: u64 },
+    /// A newer request was launched instead.
+    Superseded,
+    /// The substitute that queued the request could not be reached.
+    Unreachable(HandoffError),
+    /// The substitute speaks a protocol version this master doesn't understand.
+    Incompatible { protocol: u32 },
+}
+
+impl fmt::Display for DropReason {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DropReason::Expired { age_secs } => write!(formatter, "expired {} seconds after it was made", age_secs),
+            DropReason::Superseded => write!(formatter, "superseded by a newer launch request"),
+            DropReason::Unreachable(err) => write!(formatter, "substitute unreachable: {}", describe(err)),
+            DropReason::Incompatible { protocol } => write!(formatter, "made by a substitute speaking launch protocol {}, but this master understands {} to {}. Please update all Roblox Steam Launcher files.", protocol, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DroppedRequest {
+    pub id: String,
+    pub reason: DropReason,
+}
+
+/// What the master found in the handoff queue.
+#[derive(Debug, Default)]
+pub struct HandoffReceipt {
+    pub request: Option<LaunchRequest>,
+    pub dropped: Vec<DroppedRequest>,
+}
+
+pub fn get_handoff_file_name() -> &'static str {
+    "roblox_steam_launcher_handoff.json"
+}
+
+fn get_handoff_path(directory: &Path) -> PathBuf {
+    directory.join(get_handoff_file_name())
+}
+
+fn read_queue(handoff_path: &Path) -> Result<HandoffQueue, HandoffError> {
+    let contents = match fs::read_to_string(handoff_path) {
+        Ok(contents) => contents,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(HandoffQueue::default()),
+        Err(err) => return Err(HandoffError::Io(err)),
+    };
+    match serde_json::from_str(&contents) {
+        Ok(queue) => Ok(queue),
+        Err(err) => Err(HandoffError::Malformed(Error::new("parse the launch handoff queue").with_path(handoff_path).with_source(err))),
+    }
+}
+
+fn write_queue(handoff_path: &Path, queue: &HandoffQueue) -> Result<(), HandoffError> {
+    if queue.pending.is_empty() && queue.acknowledged.is_empty() && queue.discarded.is_empty() {
+        return match fs::remove_file(handoff_path) {
+            Ok(_) => Ok(()),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(HandoffError::Io(err)),
+        };
+    }
+    let contents = match serde_json::to_string(queue) {
+        Ok(contents) => contents,
+        Err(err) => return Err(HandoffError::Malformed(Error::new("serialize the launch handoff queue").with_source(err))),
+    };
+    write_file_atomically(handoff_path, contents.as_bytes())?;
+    Ok(())
+}
+
+/// Runs `modify` on the handoff queue while holding its lock, then writes it back.
+fn modify_queue<T, F>(handoff_path: &Path, modify: F) -> Result<T, HandoffError>
+    where F: FnOnce(&mut HandoffQueue) -> T
+{
+    let _lock = FileLock::acquire(handoff_path)?;
+    let mut queue = read_queue(handoff_path)?;
+    let result = modify(&mut queue);
+    write_queue(handoff_path, &queue)?;
+    Ok(result)
+}
+
+trait HandoffStream: Read + Write {
+    fn set_timeouts(&self, timeout: Duration) -> io::Result<()>;
+}
+
+impl HandoffStream for TcpStream {
+    fn set_timeouts(&self, timeout: Duration) -> io::Result<()> {
+        self.set_nonblocking(false)?;
+        self.set_read_timeout(Some(timeout))?;
+        self.set_write_timeout(Some(timeout))
+    }
+}
+
+#[cfg(unix)]
+impl HandoffStream for UnixStream {
+    fn set_timeouts(&self, timeout: Duration) -> io::Result<()> {
+        self.set_nonblocking(false)?;
+        self.set_read_timeout(Some(timeout))?;
+        self.set_write_timeout(Some(timeout))
+    }
+}
+
+fn write_line(stream: &mut dyn HandoffStream, line: &str) -> io::Result<()> {
+    stream.write_all(line.as_bytes())?;
+    stream.write_all(b"\n")?;
+    stream.flush()
+}
+
+fn read_line(stream: &mut dyn HandoffStream) -> io::Result<String> {
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line)?;
+    Ok(String::from(line.trim_end()))
+}
+
+enum HandoffListener {
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+    Tcp(TcpListener),
+    File,
+}
+
+impl HandoffListener {
+    fn accept(&self) -> io::Result<Box<dyn HandoffStream>> {
+        match self {
+            #[cfg(unix)]
+            HandoffListener::Unix(listener, _) => Ok(Box::new(listener.accept()?.0)),
+            HandoffListener::Tcp(listener) => Ok(Box::new(listener.accept()?.0)),
+            HandoffListener::File => Err(io::Error::new(io::ErrorKind::WouldBlock, "file handoff has no listener")),
+        }
+    }
+}
+
+/// The substitute's end of a launch handoff.
+pub struct HandoffServer {
+    handoff_path: PathBuf,
+    listener: HandoffListener,
+    request: LaunchRequest,
+}
+
+impl HandoffServer {
+    /// Starts listening for the master and queues the request in `directory`.
+    pub fn start(directory: &Path, request: LaunchRequest) -> Result<HandoffServer, HandoffError> {
+        let (listener, transport) = match HandoffServer::bind(&request.id) {
+            Ok(bound) => bound,
+            Err(_) => (HandoffListener::File, HandoffTransport::File { arguments: request.arguments.clone() }),
+        };
+        let server = HandoffServer {
+            handoff_path: get_handoff_path(directory),
+            listener,
+            request,
+        };
+        let queued = QueuedHandoff {
+            id: server.request.id.clone(),
+            created: server.request.created,
+            protocol: server.request.protocol,
+            transport,
+        };
+        modify_queue(&server.handoff_path, |queue| queue.pending.push(queued))?;
+        Ok(server)
+    }
+
+    fn bind(id: &str) -> io::Result<(HandoffListener, HandoffTransport)> {
+        #[cfg(unix)]
+        {
+            let socket_path = std::env::temp_dir().join(format!("roblox_steam_launcher_{}.sock", id));
+            if let Ok(listener) = UnixListener::bind(&socket_path) {
+                listener.set_nonblocking(true)?;
+                let transport = HandoffTransport::Unix { path: socket_path.to_string_lossy().into_owned() };
+                return Ok((HandoffListener::Unix(listener, socket_path), transport));
+            }
+        }
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let transport = HandoffTransport::Tcp { address: listener.local_addr()?.to_string() };
+        Ok((HandoffListener::Tcp(listener), transport))
+    }
+
+    pub fn request(&self) -> &LaunchRequest {
+        &self.request
+    }
+
+    /// Takes the request back out of the queue so the master can no longer pick it up, and
+    /// reports what the master did with it if it got there first.
+    pub fn withdraw(&self) -> Result<Withdrawal, HandoffError> {
+        let id = &self.request.id;
+        modify_queue(&self.handoff_path, |queue| {
+            let queued = queue.pending.len();
+            queue.pending.retain(|queued| queued.id != *id);
+            if queue.pending.len() < queued {
+                Withdrawal::Withdrawn
+            } else if queue.acknowledged.contains(id) {
+                Withdrawal::Acknowledged
+            } else if let Some(discarded) = queue.discarded.iter().find(|discarded| discarded.id == *id) {
+                Withdrawal::Discarded(discarded.reason.clone())
+            } else {
+                Withdrawal::Claimed
+            }
+        })
+    }
+
+    /// Waits until the master has received and acknowledged the request.
+    pub fn wait_for_ack(&self, timeout: Duration) -> Result<(), HandoffError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let HandoffListener::File = self.listener {
+                if read_queue(&self.handoff_path)?.acknowledged.contains(&self.request.id) {
+                    return Ok(());
+                }
+            } else {
+                match self.listener.accept() {
+                    Ok(mut stream) => {
+                        // Anything that can't prove it read our queued request is ignored.
+                        if let Ok(()) = self.serve(&mut *stream, deadline) {
+                            return Ok(());
+                        }
+                    },
+                    Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => (),
+                    Err(err) => return Err(HandoffError::Io(err)),
+                }
+            }
+            if Instant::now() >= deadline {
+                return Err(HandoffError::TimedOut);
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    fn serve(&self, stream: &mut dyn HandoffStream, deadline: Instant) -> Result<(), HandoffError> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        stream.set_timeouts(std::cmp::max(remaining, HANDOFF_CONNECT_TIMEOUT))?;
+        if read_line(stream)? != self.request.id {
+            return Err(HandoffError::Rejected(String::from("wrong request id")));
+        }
+        let request = match serde_json::to_string(&self.request) {
+            Ok(request) => request,
+            Err(err) => return Err(HandoffError::Malformed(Error::new("serialize the launch request").with_source(err))),
+        };
+        write_line(stream, &request)?;
+        let ack = read_line(stream)?;
+        if ack != ACK_MESSAGE {
+            return Err(HandoffError::Rejected(format!("unexpected acknowledgement {:?}", ack)));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for HandoffServer {
+    fn drop(&mut self) {
+        let id = &self.request.id;
+        let _ = modify_queue(&self.handoff_path, |queue| {
+            queue.pending.retain(|queued| queued.id != *id);
+            queue.acknowledged.retain(|acknowledged| acknowledged != id);
+            queue.discarded.retain(|discarded| discarded.id != *id);
+        });
+        #[cfg(unix)]
+        {
+            if let HandoffListener::Unix(_, ref socket_path) = self.listener {
+                let _ = fs::remove_file(socket_path);
+            }
+        }
+    }
+}
+
+fn receive_from_socket(queued: &QueuedHandoff, timeout: Duration) -> Result<LaunchRequest, HandoffError> {
+    let mut stream: Box<dyn HandoffStream> = match queued.transport {
+        HandoffTransport::Tcp { ref address } => match address.parse::<SocketAddr>() {
+            Ok(address) => Box::new(TcpStream::connect_timeout(&address, timeout)?),
+            Err(err) => return Err(HandoffError::Malformed(Error::new("parse the substitute's address").with_source(err))),
+        },
+        #[cfg(unix)]
+        HandoffTransport::Unix { ref path } => Box::new(UnixStream::connect(path)?),
+        #[cfg(not(unix))]
+        HandoffTransport::Unix { .. } => return Err(HandoffError::Malformed(Error::new("connect to the substitute").with_source("unix sockets are not supported on this platform"))),
+        HandoffTransport::File { .. } => return Err(HandoffError::Malformed(Error::new("connect to the substitute").with_source("file handoffs have no substitute to connect to"))),
+    };
+    stream.set_timeouts(timeout)?;
+    write_line(&mut *stream, &queued.id)?;
+    let request: LaunchRequest = match serde_json::from_str(&read_line(&mut *stream)?) {
+        Ok(request) => request,
+        Err(err) => return Err(HandoffError::Malformed(Error::new("parse the launch request").with_source(err))),
+    };
+    if request.id != queued.id || request.protocol != queued.protocol {
+        return Err(HandoffError::Rejected(String::from("request does not match the queue")));
+    }
+    write_line(&mut *stream, ACK_MESSAGE)?;
+    Ok(request)
+}
+
+/// Takes the newest unexpired launch request queued in `directory` and acknowledges it to its
+/// substitute. Every other queued request is dropped, so it can never be launched later. Only the
+/// newest request is tried: if it is incompatible, nothing is launched rather than an older
+/// request the user didn't just ask for, and if its substitute can't be reached, the substitute
+/// launches it itself once it gives up waiting.
+pub fn receive_launch_request(directory: &Path, ttl: Duration, timeout: Duration) -> Result<HandoffReceipt, HandoffError> {
+    let handoff_path = get_handoff_path(directory);
+    let mut receipt = HandoffReceipt::default();
+    // Everything drained is settled before the lock is released, whatever its transport, so no
+    // substitute is left to time out and launch a request we didn't.
+    let chosen = modify_queue(&handoff_path, |queue| {
+        let now = unix_time_now();
+        let mut chosen = None;
+        let mut newest_taken = false;
+        let mut superseded = vec![];
+        for queued in queue.pending.drain(..).rev() {
+            let age_secs = now.saturating_sub(queued.created);
+            if age_secs > ttl.as_secs() {
+                let reason = DropReason::Expired { age_secs };
+                queue.discarded.push(DiscardedHandoff { id: queued.id.clone(), reason: reason.to_string() });
+                receipt.dropped.push(DroppedRequest { id: queued.id, reason });
+            } else if newest_taken {
+                queue.discarded.push(DiscardedHandoff { id: queued.id.clone(), reason: DropReason::Superseded.to_string() });
+                superseded.push(DroppedRequest { id: queued.id, reason: DropReason::Superseded });
+            } else if queued.protocol < MIN_PROTOCOL_VERSION || queued.protocol > PROTOCOL_VERSION {
+                newest_taken = true;
+                let reason = DropReason::Incompatible { protocol: queued.protocol };
+                queue.discarded.push(DiscardedHandoff { id: queued.id.clone(), reason: reason.to_string() });
+                receipt.dropped.push(DroppedRequest { id: queued.id, reason });
+            } else {
+                newest_taken = true;
+                // File handoffs don't need their substitute, so taking one always succeeds.
+                if let HandoffTransport::File { .. } = queued.transport {
+                    queue.acknowledged.push(queued.id.clone());
+                }
+                chosen = Some(queued);
+            }
+        }
+        receipt.dropped.extend(superseded);
+        chosen
+    })?;
+    if let Some(queued) = chosen {
+        match queued.transport {
+            HandoffTransport::File { arguments } => receipt.request = Some(LaunchRequest { id: queued.id, created: queued.created, arguments, protocol: queued.protocol }),
+            _ => match receive_from_socket(&queued, timeout) {
+                Ok(request) => receipt.request = Some(request),
+                Err(err) => receipt.dropped.push(DroppedRequest { id: queued.id, reason: DropReason::Unreachable(err) }),
+            },
+        }
+    }
+    Ok(receipt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TTL: Duration = Duration::from_secs(120);
+
+    fn test_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("roblox_steam_launcher_ipc_test_{}_{}", std::process::id(), name));
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    fn arguments() -> Vec<String> {
+        vec![String::from("roblox-player:1+launchmode:play")]
+    }
+
+    fn file_server(dir: &Path, request: LaunchRequest) -> HandoffServer {
+        let server = HandoffServer {
+            handoff_path: get_handoff_path(dir),
+            listener: HandoffListener::File,
+            request,
+        };
+        let queued = QueuedHandoff {
+            id: server.request.id.clone(),
+            created: server.request.created,
+            protocol: server.request.protocol,
+            transport: HandoffTransport::File { arguments: server.request.arguments.clone() },
+        };
+        modify_queue(&server.handoff_path, |queue| queue.pending.push(queued)).unwrap();
+        server
+    }
+
+    #[test]
+    fn hands_off_over_socket() {
+        let dir = test_dir("socket");
+        let request = LaunchRequest::new(arguments());
+        let server = HandoffServer::start(&dir, request.clone()).unwrap();
+        let client_dir = dir.clone();
+        let client = thread::spawn(move || receive_launch_request(&client_dir, TTL, HANDOFF_CONNECT_TIMEOUT).unwrap());
+        server.wait_for_ack(Duration::from_secs(10)).unwrap();
+        let receipt = client.join().unwrap();
+        assert_eq!(receipt.request, Some(request));
+        assert!(receipt.dropped.is_empty());
+        drop(server);
+        assert!(!get_handoff_path(&dir).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn hands_off_through_file() {
+        let dir = test_dir("file");
+        let request = LaunchRequest::new(vec![String::from("roblox-player:1+launchmode:play+gameinfo:SECRETTICKET")]);
+        let server = file_server(&dir, request.clone());
+        assert!(fs::read_to_string(get_handoff_path(&dir)).unwrap().contains("SECRETTICKET"));
+        assert_eq!(receive_launch_request(&dir, TTL, HANDOFF_CONNECT_TIMEOUT).unwrap().request, Some(request));
+        // The ticket only stays on disk until the master has taken it.
+        assert!(!fs::read_to_string(get_handoff_path(&dir)).unwrap().contains("SECRETTICKET"));
+        server.wait_for_ack(Duration::from_secs(1)).unwrap();
+        assert_eq!(receive_launch_request(&dir, TTL, HANDOFF_CONNECT_TIMEOUT).unwrap().request, None);
+        drop(server);
+        assert!(!get_handoff_path(&dir).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn drops_expired_and_superseded_requests() {
+        let dir = test_dir("queue");
+        let mut expired = LaunchRequest::new(arguments());
+        expired.created -= TTL.as_secs() + 1;
+        let older = LaunchRequest::new(arguments());
+        let newest = LaunchRequest::new(vec![String::from("roblox-player:1+launchmode:edit")]);
+        let servers = vec![file_server(&dir, expired.clone()), file_server(&dir, older.clone()), file_server(&dir, newest.clone())];
+
+        let receipt = receive_launch_request(&dir, TTL, HANDOFF_CONNECT_TIMEOUT).unwrap();
+        assert_eq!(receipt.request, Some(newest));
+        assert_eq!(receipt.dropped.len(), 2);
+        assert_eq!(receipt.dropped[0].id, expired.id);
+        match receipt.dropped[0].reason {
+            DropReason::Expired { age_secs } => assert!(age_secs > TTL.as_secs()),
+            ref other => panic!("expected expiry, got {:?}", other),
+        }
+        assert_eq!(receipt.dropped[1].id, older.id);
+        match receipt.dropped[1].reason {
+            DropReason::Superseded => (),
+            ref other => panic!("expected supersession, got {:?}", other),
+        }
+        assert_eq!(receive_launch_request(&dir, TTL, HANDOFF_CONNECT_TIMEOUT).unwrap().request, None);
+
+        // Their substitutes are told not to launch them.
+        match servers[0].withdraw().unwrap() {
+            Withdrawal::Discarded(reason) => assert!(reason.starts_with("expired")),
+            other => panic!("expected a discarded request, got {:?}", other),
+        }
+        assert_eq!(servers[1].withdraw().unwrap(), Withdrawal::Discarded(DropReason::Superseded.to_string()));
+        assert_eq!(servers[2].withdraw().unwrap(), Withdrawal::Acknowledged);
+
+        drop(servers);
+        assert!(!get_handoff_path(&dir).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn settles_file_and_socket_requests_together() {
+        // A newer socket request supersedes an older file request.
+        let dir = test_dir("mixed_socket");
+        let file = file_server(&dir, LaunchRequest::new(arguments()));
+        let socket_request = LaunchRequest::new(arguments());
+        let socket = HandoffServer::start(&dir, socket_request.clone()).unwrap();
+        let client_dir = dir.clone();
+        let client = thread::spawn(move || receive_launch_request(&client_dir, TTL, HANDOFF_CONNECT_TIMEOUT).unwrap());
+        socket.wait_for_ack(Duration::from_secs(10)).unwrap();
+        let receipt = client.join().unwrap();
+        assert_eq!(receipt.request, Some(socket_request));
+        assert_eq!(receipt.dropped.len(), 1);
+        assert_eq!(receipt.dropped[0].id, file.request().id);
+        assert_eq!(file.withdraw().unwrap(), Withdrawal::Discarded(DropReason::Superseded.to_string()));
+        drop((file, socket));
+        assert!(!get_handoff_path(&dir).exists());
+        fs::remove_dir_all(&dir).unwrap();
+
+        // A newer file request supersedes an older socket request without connecting to it.
+        let dir = test_dir("mixed_file");
+        let socket = HandoffServer::start(&dir, LaunchRequest::new(arguments())).unwrap();
+        let file_request = LaunchRequest::new(arguments());
+        let file = file_server(&dir, file_request.clone());
+        let receipt = receive_launch_request(&dir, TTL, HANDOFF_CONNECT_TIMEOUT).unwrap();
+        assert_eq!(receipt.request, Some(file_request));
+        assert_eq!(receipt.dropped.len(), 1);
+        assert_eq!(receipt.dropped[0].id, socket.request().id);
+        file.wait_for_ack(Duration::from_secs(1)).unwrap();
+        assert_eq!(socket.withdraw().unwrap(), Withdrawal::Discarded(DropReason::Superseded.to_string()));
+        drop((file, socket));
+        assert!(!get_handoff_path(&dir).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn times_out_without_master() {
+        let dir = test_dir("timeout");
+        let server = HandoffServer::start(&dir, LaunchRequest::new(arguments())).unwrap();
+        match server.wait_for_ack(Duration::from_millis(200)) {
+            Err(HandoffError::TimedOut) => (),
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+        assert_eq!(server.withdraw().unwrap(), Withdrawal::Withdrawn);
+        assert_eq!(receive_launch_request(&dir, TTL, HANDOFF_CONNECT_TIMEOUT).unwrap().request, None);
+        drop(server);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn withdraw_loses_to_acknowledged_file_handoff() {
+        let dir = test_dir("withdraw");
+        let server = file_server(&dir, LaunchRequest::new(arguments()));
+        assert!(receive_launch_request(&dir, TTL, HANDOFF_CONNECT_TIMEOUT).unwrap().request.is_some());
+        assert_eq!(server.withdraw().unwrap(), Withdrawal::Acknowledged);
+        drop(server);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn checks_protocol_versions() {
+        let dir = test_dir("protocol");
+        let older = file_server(&dir, LaunchRequest::new(arguments()));
+        let mut request = LaunchRequest::new(arguments());
+        request.protocol = PROTOCOL_VERSION + 1;
+        let newest = file_server(&dir, request.clone());
+        let receipt = receive_launch_request(&dir, TTL, HANDOFF_CONNECT_TIMEOUT).unwrap();
+        // The older request isn't launched in place of the one the user just asked for.
+        assert_eq!(receipt.request, None);
+        match receipt.dropped[0].reason {
+            DropReason::Incompatible { protocol } => assert_eq!(protocol, PROTOCOL_VERSION + 1),
+            ref other => panic!("expected an incompatible request, got {:?}", other),
+        }
+        assert!(matches!(receipt.dropped[1].reason, DropReason::Superseded));
+        assert_eq!(newest.withdraw().unwrap(), Withdrawal::Discarded(receipt.dropped[0].reason.to_string()));
+        assert_eq!(older.withdraw().unwrap(), Withdrawal::Discarded(DropReason::Superseded.to_string()));
+        drop((older, newest));
+
+        // Requests from before the protocol was stamped are still launched.
+        let queue = format!(r#"{{"pending":[{{"id":"legacy","created":{},"transport":{{"transport":"file","arguments":[]}}}}]}}"#, unix_time_now());
+        fs::write(get_handoff_path(&dir), queue).unwrap();
+        let received = receive_launch_request(&dir, TTL, HANDOFF_CONNECT_TIMEOUT).unwrap().request.unwrap();
+        assert_eq!((received.id.as_str(), received.protocol), ("legacy", 1));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn no_request_without_handoff_file() {
+        let dir = test_dir("none");
+        let receipt = receive_launch_request(&dir, TTL, HANDOFF_CONNECT_TIMEOUT).unwrap();
+        assert_eq!(receipt.request, None);
+        assert!(receipt.dropped.is_empty());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,267 @@
+//! Makes applying and unapplying the substitute safe to interrupt.
+//!
+//! Each operation is planned as a list of steps and written to a journal next to the config
+//! before any file is touched. The journal records how many steps have finished. If the process
+//! dies part way through, `recover` finishes the operation, or undoes it if it can't be finished.
+//! A failed step is undone straight away.
+
+use serde_json;
+
+use error::Error;
+use install::{InstallError, io_error};
+use lock::FileLock;
+use write_file_atomically;
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    Apply,
+    Unapply,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Operation::Apply => write!(formatter, "apply"),
+            Operation::Unapply => write!(formatter, "unapply"),
+        }
+    }
+}
+
+/// One file operation. Every step can be run again, or undone, after it has been interrupted.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum Step {
+    /// Copies `from` to `to`, which must be a new file.
+    Copy { from: PathBuf, to: PathBuf },
+    /// Moves `from` to `to`, which must not exist.
+    Rename { from: PathBuf, to: PathBuf },
+    /// Moves `from` over `to`. What was at `to` is lost, so this can't be undone and must be the
+    /// last step.
+    Replace { from: PathBuf, to: PathBuf },
+}
+
+impl Step {
+    fn run(&self) -> Result<(), InstallError> {
+        match self {
+            Step::Copy { from, to } => fs::copy(from, to).map(|_| ()).map_err(io_error("copy", from)),
+            Step::Rename { from, to } | Step::Replace { from, to } => {
+                if !from.exists() && to.exists() {
+                    return Ok(());  // Already done before we were interrupted.
+                }
+                fs::rename(from, to).map_err(io_error("move", from))
+            },
+        }
+    }
+
+    fn undo(&self) -> Result<(), InstallError> {
+        match self {
+            Step::Copy { to, .. } => match fs::remove_file(to) {
+                Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+                result => result.map_err(io_error("remove", to)),
+            },
+            Step::Rename { from, to } => {
+                if from.exists() || !to.exists() {
+                    return Ok(());  // Never happened.
+                }
+                fs::rename(to, from).map_err(io_error("move", to))
+            },
+            Step::Replace { .. } => Ok(()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Journal {
+    pub operation: Operation,
+    pub version_path: PathBuf,
+    pub steps: Vec<Step>,
+    /// How many of `steps` have finished.
+    pub completed: usize,
+}
+
+/// What `recover` did with an unfinished journal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Recovery {
+    RolledForward(Operation, PathBuf),
+    RolledBack(Operation, PathBuf),
+}
+
+impl fmt::Display for Recovery {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Recovery::RolledForward(operation, path) => write!(formatter, "Finished an interrupted {} in {}", operation, path.display()),
+            Recovery::RolledBack(operation, path) => write!(formatter, "Undid an interrupted {} in {}", operation, path.display()),
+        }
+    }
+}
+
+pub fn get_journal_file_name() -> &'static str {
+    "roblox_steam_launcher_journal.json"
+}
+
+fn get_journal_path(versions_path: &Path) -> PathBuf {
+    versions_path.join(get_journal_file_name())
+}
+
+impl Journal {
+    pub fn new(operation: Operation, version_path: &Path, steps: Vec<Step>) -> Journal {
+        Journal {
+            operation,
+            version_path: version_path.to_path_buf(),
+            steps,
+            completed: 0,
+        }
+    }
+
+    fn write(&self, journal_path: &Path) -> Result<(), InstallError> {
+        let contents = match serde_json::to_string(self) {
+            Ok(contents) => contents,
+            Err(err) => return Err(InstallError::Io(Error::new("write").with_path(journal_path).with_source(err))),
+        };
+        write_file_atomically(journal_path, contents.as_bytes()).map_err(io_error("write", journal_path))
+    }
+
+    fn remove(journal_path: &Path) -> Result<(), InstallError> {
+        fs::remove_file(journal_path).map_err(io_error("remove", journal_path))
+    }
+
+    /// Runs the steps from `completed` on, recording progress in the journal as it goes.
+    fn roll_forward(&mut self, journal_path: &Path) -> Result<(), InstallError> {
+        while self.completed < self.steps.len() {
+            self.steps[self.completed].run()?;
+            self.completed += 1;
+            self.write(journal_path)?;
+        }
+        Ok(())
+    }
+
+    /// Undoes every step that may have run, newest first.
+    fn roll_back(&self) -> Result<(), InstallError> {
+        let started = std::cmp::min(self.completed + 1, self.steps.len());
+        for step in self.steps[..started].iter().rev() {
+            step.undo()?;
+        }
+        Ok(())
+    }
+
+    /// Journals and runs every step. If a step fails, the steps before it are undone and the
+    /// step's error is returned.
+    pub fn run(mut self, versions_path: &Path) -> Result<(), InstallError> {
+        let journal_path = get_journal_path(versions_path);
+        let _lock = FileLock::acquire(&journal_path).map_err(io_error("lock", &journal_path))?;
+        if journal_path.exists() {
+            return Err(InstallError::Io(Error::new("start").with_path(&journal_path).with_source("another operation is unfinished")));
+        }
+        self.write(&journal_path)?;
+        if let Err(err) = self.roll_forward(&journal_path) {
+            // Keep the journal if undoing fails too, so the next start can try again.
+            self.roll_back()?;
+            Journal::remove(&journal_path)?;
+            return Err(err);
+        }
+        Journal::remove(&journal_path)
+    }
+}
+
+/// Finishes or undoes an operation that was interrupted in `versions_path`. Returns `None` if
+/// there was nothing to do.
+pub fn recover(versions_path: &Path) -> Result<Option<Recovery>, InstallError> {
+    let journal_path = get_journal_path(versions_path);
+    let _lock = FileLock::acquire(&journal_path).map_err(io_error("lock", &journal_path))?;
+    let contents = match fs::read_to_string(&journal_path) {
+        Ok(contents) => contents,
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(io_error("read", &journal_path)(err)),
+    };
+    let mut journal: Journal = match serde_json::from_str(&contents) {
+        Ok(journal) => journal,
+        Err(err) => return Err(InstallError::Io(Error::new("parse").with_path(&journal_path).with_source(err))),
+    };
+    let recovery = match journal.roll_forward(&journal_path) {
+        Ok(()) => Recovery::RolledForward(journal.operation, journal.version_path.clone()),
+        Err(_) => {
+            journal.roll_back()?;
+            Recovery::RolledBack(journal.operation, journal.version_path.clone())
+        },
+    };
+    Journal::remove(&journal_path)?;
+    Ok(Some(recovery))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("roblox_steam_launcher_journal_test_{}_{}", std::process::id(), name));
+        fs::create_dir_all(path.join("version-abc")).unwrap();
+        fs::write(path.join("version-abc").join("a"), "a").unwrap();
+        path
+    }
+
+    fn steps(version: &Path) -> Vec<Step> {
+        vec![
+            Step::Copy { from: version.join("a"), to: version.join("b") },
+            Step::Rename { from: version.join("b"), to: version.join("c") },
+        ]
+    }
+
+    fn interrupted(dir: &Path, completed: usize) {
+        let version = dir.join("version-abc");
+        let mut journal = Journal::new(Operation::Apply, &version, steps(&version));
+        journal.completed = completed;
+        journal.write(&get_journal_path(dir)).unwrap();
+    }
+
+    #[test]
+    fn runs_and_removes_journal() {
+        let dir = test_dir("run");
+        let version = dir.join("version-abc");
+        Journal::new(Operation::Apply, &version, steps(&version)).run(&dir).unwrap();
+        assert!(version.join("c").is_file() && !version.join("b").exists());
+        assert!(!get_journal_path(&dir).exists());
+        assert_eq!(recover(&dir).unwrap(), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_step_is_rolled_back() {
+        let dir = test_dir("fail");
+        let version = dir.join("version-abc");
+        let mut steps = steps(&version);
+        steps.push(Step::Rename { from: version.join("missing"), to: version.join("d") });
+        assert!(Journal::new(Operation::Apply, &version, steps).run(&dir).is_err());
+        assert!(!version.join("b").exists() && !version.join("c").exists());
+        assert!(!get_journal_path(&dir).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn recovers_interrupted_operations() {
+        let dir = test_dir("forward");
+        let version = dir.join("version-abc");
+        // Interrupted after the copy.
+        fs::copy(version.join("a"), version.join("b")).unwrap();
+        interrupted(&dir, 1);
+        assert_eq!(recover(&dir).unwrap(), Some(Recovery::RolledForward(Operation::Apply, version.clone())));
+        assert!(version.join("c").is_file() && !version.join("b").exists());
+        fs::remove_dir_all(&dir).unwrap();
+
+        let dir = test_dir("back");
+        let version = dir.join("version-abc");
+        // Interrupted part way through the copy, and the source has gone since.
+        fs::write(version.join("b"), "partial").unwrap();
+        fs::remove_file(version.join("a")).unwrap();
+        interrupted(&dir, 0);
+        assert_eq!(recover(&dir).unwrap(), Some(Recovery::RolledBack(Operation::Apply, version.clone())));
+        assert!(!version.join("b").exists() && !version.join("c").exists());
+        assert!(!get_journal_path(&dir).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
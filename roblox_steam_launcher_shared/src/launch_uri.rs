@@ -0,0 +1,526 @@
+//! Parsing for the URIs the Roblox website hands to `RobloxPlayerLauncher.exe`.
+//!
+//! Two shapes are understood:
+//!
+//! * `roblox-player:1+launchmode:play+gameinfo:<ticket>+placelauncherurl:<url>+...`, a version
+//!   followed by `+`-separated `key:value` fields.
+//! * `roblox://placeId=1818&gameInstanceId=...` and `roblox://experiences/start?placeId=1818`
+//!   deep links, an optional path followed by `&`-separated `key=value` parameters.
+//!
+//! Fields are kept in their original order and encoding, so `to_string` gives back exactly what
+//! was parsed.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use std::ffi::OsStr;
+use std::fmt;
+use std::str::FromStr;
+
+const REDACTED: &str = "[REDACTED]";
+
+/// A `roblox-player:` or `roblox://` launch URI.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RobloxLaunchUri {
+    Player(PlayerUri),
+    DeepLink(DeepLinkUri),
+}
+
+/// A `roblox-player:` URI.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlayerUri {
+    /// The scheme as written, usually `roblox-player`.
+    pub scheme: String,
+    /// The protocol version before the first `+`, usually `1`.
+    pub version: String,
+    /// `key:value` fields in order. A field without a `:` has no value.
+    pub fields: Vec<(String, Option<String>)>,
+}
+
+/// A `roblox://` deep link.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeepLinkUri {
+    /// The scheme as written, usually `roblox`.
+    pub scheme: String,
+    /// Everything before the `?`, such as `experiences/start`. `None` for old-style links.
+    pub path: Option<String>,
+    /// Everything after the `?`, or the whole link for old-style links, as written. `None` if
+    /// there is no `?`, which differs from the empty query of a trailing `?`.
+    pub query: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LaunchUriError {
+    /// The string does not start with `roblox-player:` or `roblox://`.
+    UnknownScheme(String),
+}
+
+impl fmt::Display for LaunchUriError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LaunchUriError::UnknownScheme(scheme) => write!(formatter, "Not a Roblox launch URI: unknown scheme {:?}", scheme),
+        }
+    }
+}
+
+impl std::error::Error for LaunchUriError {}
+
+/// How the player was asked to start.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LaunchMode {
+    Play,
+    Edit,
+    App,
+    Other(String),
+}
+
+impl<'a> From<&'a str> for LaunchMode {
+    fn from(mode: &'a str) -> LaunchMode {
+        match mode.to_lowercase().as_str() {
+            "play" => LaunchMode::Play,
+            "edit" => LaunchMode::Edit,
+            "app" => LaunchMode::App,
+            _ => LaunchMode::Other(String::from(mode)),
+        }
+    }
+}
+
+fn find_value<'a>(pairs: &'a [(String, Option<String>)], key: &str) -> Option<&'a str> {
+    pairs.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        .and_then(|(_, value)| value.as_ref().map(|value| value.as_str()))
+}
+
+fn split_pair(pair: &str, separator: char) -> (String, Option<String>) {
+    match pair.find(separator) {
+        Some(index) => (String::from(&pair[..index]), Some(String::from(&pair[index + 1..]))),
+        None => (String::from(pair), None),
+    }
+}
+
+fn write_pairs(formatter: &mut fmt::Formatter, pairs: &[(String, Option<String>)], pair_separator: &str, separator: char) -> fmt::Result {
+    for (index, (key, value)) in pairs.iter().enumerate() {
+        if index > 0 {
+            formatter.write_str(pair_separator)?;
+        }
+        formatter.write_str(key)?;
+        if let Some(value) = value {
+            write!(formatter, "{}{}", separator, value)?;
+        }
+    }
+    Ok(())
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Decodes `%XX` escapes, leaving malformed escapes as they are.
+pub fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%' && index + 2 < bytes.len() {
+            if let (Some(high), Some(low)) = (hex_value(bytes[index + 1]), hex_value(bytes[index + 2])) {
+                decoded.push(high * 16 + low);
+                index += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[index]);
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Returns the query parameters of a URL, decoded.
+fn query_parameters(url: &str) -> Vec<(String, Option<String>)> {
+    let query = match url.find('?') {
+        Some(index) => &url[index + 1..],
+        None => return vec![],
+    };
+    query.split('&')
+        .map(|pair| {
+            let (key, value) = split_pair(pair, '=');
+            (percent_decode(&key), value.map(|value| percent_decode(&value)))
+        })
+        .collect()
+}
+
+impl PlayerUri {
+    pub fn field(&self, key: &str) -> Option<&str> {
+        find_value(&self.fields, key)
+    }
+
+    /// The decoded `PlaceLauncher.ashx` URL, which holds the place and job ids.
+    pub fn place_launcher_url(&self) -> Option<String> {
+        self.field("placelauncherurl").map(percent_decode)
+    }
+
+    fn place_launcher_parameter(&self, key: &str) -> Option<String> {
+        let url = self.place_launcher_url()?;
+        let parameters = query_parameters(&url);
+        find_value(&parameters, key).map(String::from)
+    }
+}
+
+impl DeepLinkUri {
+    /// The value of the `&`-separated `key=value` parameter `key`. A parameter without a `=` has
+    /// no value.
+    pub fn parameter(&self, key: &str) -> Option<&str> {
+        self.query.as_ref()?.split('&')
+            .map(|pair| match pair.find('=') {
+                Some(index) => (&pair[..index], Some(&pair[index + 1..])),
+                None => (pair, None),
+            })
+            .find(|(name, _)| name.eq_ignore_ascii_case(key))
+            .and_then(|(_, value)| value)
+    }
+}
+
+impl RobloxLaunchUri {
+    /// The launch mode. Deep links always start the player.
+    pub fn launch_mode(&self) -> Option<LaunchMode> {
+        match self {
+            RobloxLaunchUri::Player(uri) => uri.field("launchmode").map(LaunchMode::from),
+            RobloxLaunchUri::DeepLink(_) => Some(LaunchMode::Play),
+        }
+    }
+
+    pub fn place_id(&self) -> Option<u64> {
+        match self {
+            RobloxLaunchUri::Player(uri) => uri.place_launcher_parameter("placeId"),
+            RobloxLaunchUri::DeepLink(uri) => uri.parameter("placeId").map(percent_decode),
+        }.and_then(|place_id| place_id.parse().ok())
+    }
+
+    /// The id of the specific server to join, if the launch targets one.
+    pub fn job_id(&self) -> Option<String> {
+        match self {
+            RobloxLaunchUri::Player(uri) => uri.place_launcher_parameter("gameId"),
+            RobloxLaunchUri::DeepLink(uri) => uri.parameter("gameInstanceId").map(percent_decode),
+        }
+    }
+
+    /// The authentication ticket. Never log or store this.
+    pub fn auth_ticket(&self) -> Option<&str> {
+        match self {
+            RobloxLaunchUri::Player(uri) => uri.field("gameinfo"),
+            RobloxLaunchUri::DeepLink(_) => None,
+        }
+    }
+
+    pub fn launch_time(&self) -> Option<u64> {
+        match self {
+            RobloxLaunchUri::Player(uri) => uri.field("launchtime").and_then(|time| time.parse().ok()),
+            RobloxLaunchUri::DeepLink(_) => None,
+        }
+    }
+
+    pub fn roblox_locale(&self) -> Option<&str> {
+        match self {
+            RobloxLaunchUri::Player(uri) => uri.field("robloxLocale"),
+            RobloxLaunchUri::DeepLink(_) => None,
+        }
+    }
+
+    pub fn game_locale(&self) -> Option<&str> {
+        match self {
+            RobloxLaunchUri::Player(uri) => uri.field("gameLocale"),
+            RobloxLaunchUri::DeepLink(_) => None,
+        }
+    }
+
+    pub fn channel(&self) -> Option<&str> {
+        match self {
+            RobloxLaunchUri::Player(uri) => uri.field("channel"),
+            RobloxLaunchUri::DeepLink(_) => None,
+        }
+    }
+
+    /// Returns a copy with the authentication ticket replaced, safe for logging.
+    pub fn redacted(&self) -> RobloxLaunchUri {
+        let mut redacted = self.clone();
+        if let RobloxLaunchUri::Player(ref mut uri) = redacted {
+            for (key, value) in uri.fields.iter_mut() {
+                if key.eq_ignore_ascii_case("gameinfo") && value.is_some() {
+                    *value = Some(String::from(REDACTED));
+                }
+            }
+        }
+        redacted
+    }
+
+    /// Returns the URI as a string with the authentication ticket replaced, safe for logging.
+    pub fn to_redacted_string(&self) -> String {
+        self.redacted().to_string()
+    }
+}
+
+impl FromStr for RobloxLaunchUri {
+    type Err = LaunchUriError;
+
+    fn from_str(uri: &str) -> Result<RobloxLaunchUri, LaunchUriError> {
+        let (scheme, rest) = match uri.find(':') {
+            Some(index) => (&uri[..index], &uri[index + 1..]),
+            None => return Err(LaunchUriError::UnknownScheme(String::from(uri))),
+        };
+        if scheme.eq_ignore_ascii_case("roblox-player") {
+            let mut segments = rest.split('+');
+            let version = String::from(segments.next().unwrap_or(""));
+            let fields = segments.map(|segment| split_pair(segment, ':')).collect();
+            Ok(RobloxLaunchUri::Player(PlayerUri { scheme: String::from(scheme), version, fields }))
+        } else if scheme.eq_ignore_ascii_case("roblox") && rest.starts_with("//") {
+            let rest = &rest[2..];
+            let (path, query) = match rest.find('?') {
+                Some(index) => (Some(String::from(&rest[..index])), Some(String::from(&rest[index + 1..]))),
+                None if rest.contains('=') => (None, Some(String::from(rest))),
+                None => (Some(String::from(rest)), None),
+            };
+            Ok(RobloxLaunchUri::DeepLink(DeepLinkUri { scheme: String::from(scheme), path, query }))
+        } else {
+            Err(LaunchUriError::UnknownScheme(String::from(scheme)))
+        }
+    }
+}
+
+impl fmt::Display for RobloxLaunchUri {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RobloxLaunchUri::Player(uri) => {
+                write!(formatter, "{}:{}", uri.scheme, uri.version)?;
+                if !uri.fields.is_empty() {
+                    formatter.write_str("+")?;
+                }
+                write_pairs(formatter, &uri.fields, "+", ':')
+            },
+            RobloxLaunchUri::DeepLink(uri) => {
+                write!(formatter, "{}://", uri.scheme)?;
+                if let Some(ref path) = uri.path {
+                    formatter.write_str(path)?;
+                    if uri.query.is_some() {
+                        formatter.write_str("?")?;
+                    }
+                }
+                if let Some(ref query) = uri.query {
+                    formatter.write_str(query)?;
+                }
+                Ok(())
+            },
+        }
+    }
+}
+
+/// Flags `RobloxPlayerLauncher.exe` takes besides a launch URI. `-app` opens the Roblox app.
+pub const KNOWN_FLAGS: &[&str] = &["-app"];
+
+/// One argument for `RobloxPlayerLauncher.exe`. A `roblox-player:` URI carries the player's
+/// authentication ticket, so `Debug` and `Display` show the argument with the ticket replaced.
+/// `expose` gives the argument itself, which should only be passed on to Roblox.
+#[derive(Clone, PartialEq, Eq)]
+pub struct LaunchArgument(String);
+
+impl LaunchArgument {
+    pub fn new<S: Into<String>>(argument: S) -> LaunchArgument {
+        LaunchArgument(argument.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// The argument as a launch URI, if it is one.
+    pub fn uri(&self) -> Option<RobloxLaunchUri> {
+        self.0.parse().ok()
+    }
+
+    pub fn is_known_flag(&self) -> bool {
+        KNOWN_FLAGS.iter().any(|flag| flag.eq_ignore_ascii_case(&self.0))
+    }
+
+    /// Returns the argument with the authentication ticket replaced, safe for logging. An
+    /// argument that mentions a ticket but can't be parsed is replaced entirely.
+    pub fn to_redacted_string(&self) -> String {
+        match self.uri() {
+            Some(uri) => uri.to_redacted_string(),
+            None if self.0.to_ascii_lowercase().contains("gameinfo") => String::from(REDACTED),
+            None => self.0.clone(),
+        }
+    }
+}
+
+impl From<String> for LaunchArgument {
+    fn from(argument: String) -> LaunchArgument {
+        LaunchArgument(argument)
+    }
+}
+
+impl AsRef<OsStr> for LaunchArgument {
+    fn as_ref(&self) -> &OsStr {
+        OsStr::new(&self.0)
+    }
+}
+
+impl fmt::Display for LaunchArgument {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(&self.to_redacted_string())
+    }
+}
+
+impl fmt::Debug for LaunchArgument {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{:?}", self.to_redacted_string())
+    }
+}
+
+impl Serialize for LaunchArgument {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for LaunchArgument {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<LaunchArgument, D::Error> {
+        String::deserialize(deserializer).map(LaunchArgument)
+    }
+}
+
+/// Why launch arguments don't look like something the Roblox website would pass.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArgumentError {
+    /// An argument that is neither a launch URI nor a known flag, redacted.
+    Unrecognized(String),
+    /// More than one launch URI.
+    MultipleUris(usize),
+}
+
+impl fmt::Display for ArgumentError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArgumentError::Unrecognized(argument) => write!(formatter, "Unrecognized launch argument {:?}", argument),
+            ArgumentError::MultipleUris(count) => write!(formatter, "Expected one launch URI, but got {}", count),
+        }
+    }
+}
+
+impl std::error::Error for ArgumentError {}
+
+/// Checks that `arguments` are at most one launch URI and any of `KNOWN_FLAGS`, so nothing else
+/// can be slipped into Roblox's command line.
+pub fn check_arguments(arguments: &[LaunchArgument]) -> Result<(), ArgumentError> {
+    let mut uris = 0;
+    for argument in arguments {
+        if argument.uri().is_some() {
+            uris += 1;
+        } else if !argument.is_known_flag() {
+            return Err(ArgumentError::Unrecognized(argument.to_redacted_string()));
+        }
+    }
+    if uris > 1 {
+        return Err(ArgumentError::MultipleUris(uris));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAYER_URI: &str = "roblox-player:1+launchmode:play+gameinfo:SECRETTICKET+launchtime:1500000000000+placelauncherurl:https%3A%2F%2Fassetgame.roblox.com%2Fgame%2FPlaceLauncher.ashx%3Frequest%3DRequestGameJob%26browserTrackerId%3D123%26placeId%3D1818%26gameId%3Dabc-def%26isPlayTogetherGame%3Dfalse+browsertrackerid:123+robloxLocale:en_us+gameLocale:en_us+channel:";
+
+    fn parse(uri: &str) -> RobloxLaunchUri {
+        match uri.parse() {
+            Ok(uri) => uri,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    #[test]
+    fn parses_player_uri() {
+        let uri = parse(PLAYER_URI);
+        assert_eq!(uri.launch_mode(), Some(LaunchMode::Play));
+        assert_eq!(uri.auth_ticket(), Some("SECRETTICKET"));
+        assert_eq!(uri.launch_time(), Some(1500000000000));
+        assert_eq!(uri.place_id(), Some(1818));
+        assert_eq!(uri.job_id(), Some(String::from("abc-def")));
+        assert_eq!(uri.roblox_locale(), Some("en_us"));
+        assert_eq!(uri.game_locale(), Some("en_us"));
+        assert_eq!(uri.channel(), Some(""));
+        assert_eq!(uri.to_string(), PLAYER_URI);
+    }
+
+    #[test]
+    fn checks_launch_arguments() {
+        let arguments = |arguments: &[&str]| arguments.iter().map(|argument| LaunchArgument::new(*argument)).collect::<Vec<_>>();
+        assert_eq!(check_arguments(&arguments(&[])), Ok(()));
+        assert_eq!(check_arguments(&arguments(&[PLAYER_URI])), Ok(()));
+        assert_eq!(check_arguments(&arguments(&["roblox://placeId=1818"])), Ok(()));
+        assert_eq!(check_arguments(&arguments(&["-APP"])), Ok(()));
+        assert_eq!(check_arguments(&arguments(&[PLAYER_URI, "--inject", "gameinfo:SECRETTICKET"])), Err(ArgumentError::Unrecognized(String::from("--inject"))));
+        assert_eq!(check_arguments(&arguments(&["gameinfo:SECRETTICKET"])), Err(ArgumentError::Unrecognized(String::from(REDACTED))));
+        assert_eq!(check_arguments(&arguments(&[PLAYER_URI, "roblox://placeId=1818"])), Err(ArgumentError::MultipleUris(2)));
+    }
+
+    #[test]
+    fn launch_arguments_hide_auth_ticket() {
+        let argument = LaunchArgument::new(PLAYER_URI);
+        assert!(!format!("{}", argument).contains("SECRETTICKET"));
+        assert!(!format!("{:?}", argument).contains("SECRETTICKET"));
+        assert!(!format!("{:?}", vec![LaunchArgument::new("not-a-uri+gameinfo:SECRETTICKET")]).contains("SECRETTICKET"));
+        assert_eq!(format!("{}", LaunchArgument::new("-app")), "-app");
+        assert_eq!(argument.expose(), PLAYER_URI);
+        let serialized = ::serde_json::to_string(&argument).unwrap();
+        assert!(serialized.contains("SECRETTICKET"));
+        assert_eq!(::serde_json::from_str::<LaunchArgument>(&serialized).unwrap(), argument);
+    }
+
+    #[test]
+    fn redacts_auth_ticket() {
+        let uri = parse(PLAYER_URI);
+        let redacted = uri.to_redacted_string();
+        assert!(!redacted.contains("SECRETTICKET"));
+        assert_eq!(redacted, PLAYER_URI.replace("SECRETTICKET", REDACTED));
+        assert_eq!(parse(&redacted).place_id(), Some(1818));
+    }
+
+    #[test]
+    fn parses_deep_links() {
+        let old_style = "roblox://placeId=1818&gameInstanceId=abc-def";
+        let uri = parse(old_style);
+        assert_eq!(uri.place_id(), Some(1818));
+        assert_eq!(uri.job_id(), Some(String::from("abc-def")));
+        assert_eq!(uri.auth_ticket(), None);
+        assert_eq!(uri.to_string(), old_style);
+
+        let new_style = "roblox://experiences/start?placeId=1818";
+        let uri = parse(new_style);
+        assert_eq!(uri.place_id(), Some(1818));
+        assert_eq!(uri.job_id(), None);
+        assert_eq!(uri.to_string(), new_style);
+    }
+
+    #[test]
+    fn round_trips_odd_input() {
+        for uri in &["roblox-player:1", "roblox-player:1+flag+key:a:b++", "roblox://", "roblox://path", "roblox://path?", "roblox://?", "roblox://path?flag&=", "ROBLOX-PLAYER:2+launchmode:EDIT"] {
+            assert_eq!(parse(uri).to_string(), *uri);
+        }
+        assert_eq!(parse("ROBLOX-PLAYER:2+launchmode:EDIT").launch_mode(), Some(LaunchMode::Edit));
+    }
+
+    #[test]
+    fn rejects_other_schemes() {
+        assert_eq!("https://www.roblox.com".parse::<RobloxLaunchUri>(), Err(LaunchUriError::UnknownScheme(String::from("https"))));
+        assert_eq!("-app".parse::<RobloxLaunchUri>(), Err(LaunchUriError::UnknownScheme(String::from("-app"))));
+    }
+
+    #[test]
+    fn decodes_percent_escapes() {
+        assert_eq!(percent_decode("a%20b%2"), "a b%2");
+        assert_eq!(percent_decode("%E5%B1%B1"), "山");
+    }
+}
@@ -3,96 +3,49 @@ extern crate serde_derive;
 
 extern crate serde;
 extern crate serde_json;
+extern crate crc;
+extern crate fs2;
+#[cfg(windows)]
+extern crate winreg;
+
+pub mod capabilities;
+pub mod error;
+pub mod fallback;
+pub mod gameid;
+pub mod install;
+pub mod ipc;
+pub mod journal;
+pub mod launch_uri;
+#[macro_use]
+pub mod logging;
+pub mod platform;
+pub mod steam_launch;
+pub mod supervise;
+pub mod versions;
+mod config;
+mod lock;
+
+pub use config::*;
+pub use lock::*;
+
+use platform::{CommandError, Platform};
+use steam_launch::SteamLaunchStrategy;
 
-use std::fs::OpenOptions;
 use std::env;
+use std::ffi::OsStr;
+use std::fs::OpenOptions;
+use std::io;
 use std::io::prelude::*;
 use std::process::Command;
-use std::path::PathBuf;
-use std::error::Error;
-use std::fmt;
-
-
-#[derive(Clone,Debug,PartialEq,Serialize, Deserialize)]
-pub struct Config {
-    pub steam_gameid: String,
-    pub debug: bool,
-    pub arguments: Vec<String>,
-}
-
-#[derive(Clone)]
-pub enum ConfigReadError {
-    Malformed(String),
-    NotReadable(String),
-    NotOpenable(String),
-}
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-impl std::fmt::Display for ConfigReadError {
-    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &ConfigReadError::Malformed(ref err) => write!(formatter, "Malformed config file: {}", err),
-            &ConfigReadError::NotReadable(ref err) => write!(formatter, "Cannot read config file: {}", err),
-            &ConfigReadError::NotOpenable(ref err) => write!(formatter, "Cannot open config file for reading: {}", err),
-        }
-    }
-}
-
-#[derive(Clone)]
-pub enum ConfigWriteError {
-    NotSerializable(String),
-    NotWriteable(String),
-    NotOpenable(String),
-}
 
-impl std::fmt::Display for ConfigWriteError {
-    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &ConfigWriteError::NotSerializable(ref err) => write!(formatter, "Cannot serialize config struct: {}", err),
-            &ConfigWriteError::NotWriteable(ref err) => write!(formatter, "Cannot write config file: {}", err),
-            &ConfigWriteError::NotOpenable(ref err) => write!(formatter, "Cannot open config file for writing: {}", err),
-        }
-    }
-}
-
-impl Config {
-    pub fn new() -> Config {
-        Config {
-            steam_gameid: String::new(),
-            debug: false,
-            arguments: vec![],
-        }
-    }
-    pub fn from_path(path: &PathBuf) -> Result<Config, ConfigReadError> {
-        return match OpenOptions::new().read(true).write(false).open(path) {
-            Ok(mut read_file) => {
-                let mut contents = String::new();
-                match read_file.read_to_string(&mut contents) {
-                    Ok(_) => {
-                        match serde_json::from_str(&contents) {
-                            Ok(config) => Ok(config),
-                            Err(err) => Err(ConfigReadError::Malformed(String::from(err.description())))
-                        }
-                    },
-                    Err(err) => Err(ConfigReadError::NotReadable(String::from(err.description())))
-                }
-            },
-            Err(err) => Err(ConfigReadError::NotOpenable(String::from(err.description())))
-        }
-    }
-    pub fn write_to_path(&self, path: &PathBuf) -> Result<(), ConfigWriteError> {
-        match OpenOptions::new().read(false).write(true).create(true).truncate(true).open(path) {
-            Ok(mut write_file) => {
-                let config_as_str = match serde_json::to_string(self) {
-                    Ok(as_str) => as_str,
-                    Err(err) => return Err(ConfigWriteError::NotSerializable(String::from(err.description())))
-                };
-                match write_file.write_all(config_as_str.as_bytes()) {
-                    Ok(_) => return Ok(()),
-                    Err(err) => return Err(ConfigWriteError::NotWriteable(String::from(err.description()))),
-                };
-            },
-            Err(err) => return Err(ConfigWriteError::NotOpenable(String::from(err.description()))),
-        }
+/// The current time in seconds since the Unix epoch, or 0 if the clock is set before it.
+pub fn unix_time_now() -> u64 {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs(),
+        Err(_) => 0,
     }
 }
 
@@ -111,17 +64,44 @@ pub fn get_intended_arguments(args: &mut env::Args) -> Vec<String> {
     args.collect::<Vec<_>>().split_off(1)
 }
 
+/// Writes `contents` to a temporary file next to `path` and then moves it over `path`, so readers
+/// never see a partially written file.
+///
+/// The config and handoff queue can hold authentication tickets, so on Unix the file is only
+/// readable by its owner. On Windows it inherits the Roblox directory's per-user permissions.
+pub fn write_file_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temp_file_name = path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    temp_file_name.push(format!(".{}.tmp", std::process::id()));
+    let temp_path = path.with_file_name(temp_file_name);
+    let mut options = OpenOptions::new();
+    options.read(false).write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let result = options.open(&temp_path)
+        .and_then(|mut write_file| write_file.write_all(contents).and_then(|_| write_file.sync_all()))
+        .and_then(|_| std::fs::rename(&temp_path, path));
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
 pub fn get_config_file_name() -> &'static str {
     "roblox_steam_launcher_config.json"
 }
 
-pub fn launch_steam(game_id: String) -> std::io::Result<std::process::Child> {
-    Command::new("explorer.exe")
-        .arg(format!("steam://rungameid/{}", game_id))
-        .spawn()
+pub fn get_update_cache_file_name() -> &'static str {
+    "roblox_steam_launcher_update_cache.json"
+}
+
+pub fn launch_steam(platform: &dyn Platform, strategy: &SteamLaunchStrategy, game_id: &str) -> Result<(), CommandError> {
+    strategy.launch(platform, game_id)
 }
 
-pub fn launch_game(game_path: &PathBuf, args: &Vec<String>) -> std::io::Result<std::process::Child> {
+pub fn launch_game<S: AsRef<OsStr>>(game_path: &PathBuf, args: &[S]) -> std::io::Result<std::process::Child> {
     let mut working_path = game_path.clone();
     working_path.pop();
     Command::new(game_path)
@@ -129,51 +109,3 @@ pub fn launch_game(game_path: &PathBuf, args: &Vec<String>) -> std::io::Result<s
         .current_dir(working_path)
         .spawn()
 }
-
-pub fn get_newest_roblox_player_directory_path(versions_path: &PathBuf) -> Option<PathBuf> {
-    let mut newest_dir = None;
-    for current_dir_opt in versions_path.read_dir().expect("Failed to iterate over directory") {
-        if let Ok(current_dir) = current_dir_opt {
-            if current_dir.path().is_dir() {
-                let mut player_path = current_dir.path().clone();
-                player_path.push("RobloxPlayerLauncher.exe");
-                if player_path.exists() {
-                    if let Ok(current_metadata) = current_dir.metadata() {
-                        if let Ok(current_created) = current_metadata.created() {
-                            newest_dir = match newest_dir {
-                                None => Some((current_dir, current_created)),
-                                Some((newest_dir, newest_created)) => {
-                                    if current_created > newest_created {
-                                        Some((current_dir, current_created))
-                                    } else {
-                                        None
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
-        }
-    }
-    match newest_dir {
-        None => None,
-        Some((newest_dir, newest_created)) => Some(newest_dir.path()),
-    }
-}
-
-pub fn get_newest_roblox_player_executable_path(versions_path: &PathBuf) -> Option<PathBuf> {
-    let folder = get_newest_roblox_player_directory_path(versions_path);
-    match folder {
-        None => None,
-        Some(path) => {
-            let mut new_path = path.clone();
-            new_path.push("RobloxPlayerLauncher_original.exe");
-            if new_path.is_file() {
-                Some(new_path)
-            } else {
-                None
-            }
-        }
-    }
-}
@@ -0,0 +1,67 @@
+use fs2::FileExt;
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long to wait for another launcher process to release a lock.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Returns the path of the lock file guarding read-modify-write sequences on `path`.
+pub fn get_lock_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    file_name.push(".lock");
+    path.with_file_name(file_name)
+}
+
+/// An exclusive advisory lock on a shared state file, released when dropped.
+///
+/// The lock is taken on a separate lock file so that the file itself can be replaced atomically
+/// while the lock is held. The operating system releases the lock if the process dies.
+pub struct FileLock {
+    file: File,
+}
+
+impl FileLock {
+    /// Locks the file at `path`, waiting up to `LOCK_TIMEOUT` for other processes.
+    pub fn acquire(path: &Path) -> io::Result<FileLock> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(get_lock_path(path))?;
+        let start = Instant::now();
+        loop {
+            match file.try_lock_exclusive() {
+                Ok(_) => return Ok(FileLock { file }),
+                Err(_) if start.elapsed() < LOCK_TIMEOUT => thread::sleep(Duration::from_millis(50)),
+                Err(err) => return Err(io::Error::new(io::ErrorKind::TimedOut, err)),
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn lock_is_exclusive() {
+        let dir = std::env::temp_dir().join(format!("roblox_steam_launcher_lock_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.json");
+        let lock = FileLock::acquire(&path).unwrap();
+
+        let other = File::open(get_lock_path(&path)).unwrap();
+        assert!(other.try_lock_exclusive().is_err());
+        drop(lock);
+        assert!(other.try_lock_exclusive().is_ok());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,298 @@
+//! A log file next to the config, so a launch that went wrong can be looked into after the
+//! console has closed, or when there was never a console at all.
+//!
+//! Each line has a timestamp, a level, and the component that wrote it, such as `master` or
+//! `substitute`. Every component writes to the same file. Once the file reaches
+//! `MAX_LOG_FILE_BYTES` it is moved aside, keeping `LOG_FILE_GENERATIONS` old files.
+//!
+//! Logging never fails the caller. A line that can't be written is dropped.
+
+use lock::FileLock;
+
+use std::fmt;
+use std::fs;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::prelude::*;
+use std::panic;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How large the log file may grow before it is rotated.
+pub const MAX_LOG_FILE_BYTES: u64 = 1024 * 1024;
+/// How many rotated log files are kept besides the current one.
+pub const LOG_FILE_GENERATIONS: u32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl Level {
+    /// The level to log at for the config's `debug` setting.
+    pub fn from_debug(debug: bool) -> Level {
+        if debug {
+            Level::Debug
+        } else {
+            Level::Info
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Level::Error => formatter.pad("ERROR"),
+            Level::Warn => formatter.pad("WARN"),
+            Level::Info => formatter.pad("INFO"),
+            Level::Debug => formatter.pad("DEBUG"),
+        }
+    }
+}
+
+pub fn get_log_file_name() -> &'static str {
+    "roblox_steam_launcher.log"
+}
+
+/// The path of the log file `generation` rotations old. Generation 0 is the current file.
+pub fn get_log_path(directory: &Path, generation: u32) -> PathBuf {
+    if generation == 0 {
+        directory.join(get_log_file_name())
+    } else {
+        directory.join(format!("{}.{}", get_log_file_name(), generation))
+    }
+}
+
+struct Logger {
+    component: &'static str,
+    directory: PathBuf,
+    level: Level,
+    /// Lines at or above this level are printed as well as written.
+    console: Option<Level>,
+}
+
+static LOGGER: Mutex<Option<Logger>> = Mutex::new(None);
+
+fn logger() -> MutexGuard<'static, Option<Logger>> {
+    match LOGGER.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
+
+/// Starts writing lines at `level` or above to the log file in `directory`, as `component`.
+pub fn init(component: &'static str, directory: &Path, level: Level) {
+    *logger() = Some(Logger {
+        component,
+        directory: directory.to_path_buf(),
+        level,
+        console: None,
+    });
+}
+
+/// Changes the level, such as once the config has been read.
+pub fn set_level(level: Level) {
+    if let Some(logger) = logger().as_mut() {
+        logger.level = level;
+    }
+}
+
+/// Prints lines at `level` or above to stdout too, or stops printing them if `None`.
+pub fn set_console_level(level: Option<Level>) {
+    if let Some(logger) = logger().as_mut() {
+        logger.console = level;
+    }
+}
+
+/// Writes a line if logging has started and `level` is enabled. Use the `log_*!` macros instead.
+pub fn log(level: Level, message: fmt::Arguments) {
+    write_to(&logger(), level, message);
+}
+
+fn write_to(logger: &Option<Logger>, level: Level, message: fmt::Arguments) {
+    let logger = match logger {
+        Some(logger) => logger,
+        None => return,
+    };
+    if matches!(logger.console, Some(console) if level <= console) {
+        println!("{}", message);
+    }
+    if level > logger.level {
+        return;
+    }
+    let line = format_line(SystemTime::now(), level, logger.component, message);
+    let _ = append(&logger.directory, &line, MAX_LOG_FILE_BYTES, LOG_FILE_GENERATIONS);
+}
+
+/// Logs panics as errors before the usual panic message is printed.
+pub fn log_panics() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        // The panic may have happened while the logger was locked, so don't wait for it.
+        if let Ok(logger) = LOGGER.try_lock() {
+            write_to(&logger, Level::Error, format_args!("{}", info));
+        }
+        previous(info);
+    }));
+}
+
+fn format_line(time: SystemTime, level: Level, component: &str, message: fmt::Arguments) -> String {
+    format!("{} {:<5} {}[{}]: {}\n", format_timestamp(time), level, component, std::process::id(), message)
+}
+
+/// Formats `time` as an RFC 3339 UTC timestamp with milliseconds.
+fn format_timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = since_epoch.as_secs();
+    let (year, month, day) = civil_from_days(seconds / 86_400);
+    let seconds_of_day = seconds % 86_400;
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z", year, month, day, seconds_of_day / 3600, seconds_of_day / 60 % 60, seconds_of_day % 60, since_epoch.subsec_millis())
+}
+
+/// The year, month and day `days` after 1970-01-01, in the proleptic Gregorian calendar.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    // Counts from 0000-03-01, so leap days fall at the end of each year.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = era * 400 + year_of_era + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Makes a log file readable only by its owner. Log lines can describe what was launched, so like
+/// the config they are kept from other users on Unix.
+#[cfg(unix)]
+fn restrict_permissions(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Moves each log file one generation older, dropping the oldest, and keeps each of them readable
+/// only by its owner.
+fn rotate(directory: &Path, generations: u32) -> io::Result<()> {
+    if let Err(err) = fs::remove_file(get_log_path(directory, generations)) {
+        if err.kind() != io::ErrorKind::NotFound {
+            return Err(err);
+        }
+    }
+    for generation in (0..generations).rev() {
+        let from = get_log_path(directory, generation);
+        if from.exists() {
+            let to = get_log_path(directory, generation + 1);
+            fs::rename(&from, &to)?;
+            restrict_permissions(&to)?;
+        }
+    }
+    Ok(())
+}
+
+/// Appends `line` to the log file in `directory`, rotating it first if it would grow past
+/// `max_bytes`. The substitute and the master may log at the same time, so this is done under
+/// a lock.
+fn append(directory: &Path, line: &str, max_bytes: u64, generations: u32) -> io::Result<()> {
+    let path = get_log_path(directory, 0);
+    let _lock = FileLock::acquire(&path)?;
+    let size = match fs::metadata(&path) {
+        Ok(metadata) => metadata.len(),
+        Err(_) => 0,
+    };
+    if size > 0 && size + line.len() as u64 > max_bytes {
+        rotate(directory, generations)?;
+    }
+    let mut options = OpenOptions::new();
+    options.append(true).create(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&path)?;
+    // The mode only applies when the file is created, so restrict one that already existed too.
+    restrict_permissions(&path)?;
+    file.write_all(line.as_bytes())
+}
+
+#[macro_export]
+macro_rules! log_error {
+    ($($arg:tt)+) => ($crate::logging::log($crate::logging::Level::Error, format_args!($($arg)+)))
+}
+
+#[macro_export]
+macro_rules! log_warn {
+    ($($arg:tt)+) => ($crate::logging::log($crate::logging::Level::Warn, format_args!($($arg)+)))
+}
+
+#[macro_export]
+macro_rules! log_info {
+    ($($arg:tt)+) => ($crate::logging::log($crate::logging::Level::Info, format_args!($($arg)+)))
+}
+
+#[macro_export]
+macro_rules! log_debug {
+    ($($arg:tt)+) => ($crate::logging::log($crate::logging::Level::Debug, format_args!($($arg)+)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn formats_lines() {
+        assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        assert_eq!(format_timestamp(UNIX_EPOCH + Duration::from_millis(1_792_314_303_045)), "2026-10-18T09:05:03.045Z");
+        assert_eq!(format_timestamp(UNIX_EPOCH + Duration::from_millis(1_709_251_199_999)), "2024-02-29T23:59:59.999Z");
+        let line = format_line(UNIX_EPOCH, Level::Warn, "master", format_args!("hello {}", 1));
+        assert_eq!(line, format!("1970-01-01T00:00:00.000Z WARN  master[{}]: hello 1\n", std::process::id()));
+        assert!(Level::Error < Level::Debug);
+        assert_eq!(Level::from_debug(false), Level::Info);
+    }
+
+    #[test]
+    fn rotates_log_files() {
+        let dir = std::env::temp_dir().join(format!("roblox_steam_launcher_logging_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(get_log_path(&dir, 0), "line 0\n").unwrap();
+        for index in 1..5 {
+            append(&dir, &format!("line {}\n", index), 10, 2).unwrap();
+        }
+        assert_eq!(fs::read_to_string(get_log_path(&dir, 0)).unwrap(), "line 4\n");
+        assert_eq!(fs::read_to_string(get_log_path(&dir, 1)).unwrap(), "line 3\n");
+        assert_eq!(fs::read_to_string(get_log_path(&dir, 2)).unwrap(), "line 2\n");
+        assert!(!get_log_path(&dir, 3).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn restricts_log_files() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("roblox_steam_launcher_logging_test_{}_restricts", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mode = |generation| fs::metadata(get_log_path(&dir, generation)).unwrap().permissions().mode() & 0o777;
+        fs::write(get_log_path(&dir, 0), "line 0\n").unwrap();
+        fs::set_permissions(get_log_path(&dir, 0), fs::Permissions::from_mode(0o644)).unwrap();
+        append(&dir, "line 1\n", 1000, 2).unwrap();
+        assert_eq!(mode(0), 0o600);
+        fs::set_permissions(get_log_path(&dir, 0), fs::Permissions::from_mode(0o644)).unwrap();
+        append(&dir, "line 2\n", 10, 2).unwrap();
+        assert_eq!(mode(0), 0o600);
+        assert_eq!(mode(1), 0o600);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,638 @@
+//! Everything that differs between operating systems: opening URLs, reading the registry,
+//! listing processes, and finding Steam and Roblox.
+//!
+//! `current_platform` picks the implementation for the running system. Under Wine the launcher is
+//! a Windows program and uses `WindowsPlatform`; `LinuxPlatform` is for native builds, which find
+//! Roblox inside a Wine prefix. `FakePlatform` keeps everything in memory for tests.
+
+use versions::{find_newest_player_version, VersionOrder};
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const STEAM_REGISTRY_KEY: &str = "Software\\Valve\\Steam";
+pub const ROBLOX_REGISTRY_KEY: &str = "Software\\RobloxReg";
+
+/// How long `run_command` waits for a command to report success or failure.
+pub const COMMAND_ACCEPT_WAIT: Duration = Duration::from_secs(10);
+
+/// How often `wait_for_process` checks on a process it can't be notified about.
+const PROCESS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Where Roblox is installed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RobloxDirectories {
+    /// The `Versions` directory, which also holds the launcher's own files.
+    pub versions: PathBuf,
+    /// The `RobloxPlayerLauncher.exe` the website currently launches.
+    pub current_launcher: PathBuf,
+}
+
+impl RobloxDirectories {
+    /// Derives the directories from the path of the current launcher, two levels below `Versions`.
+    pub fn from_launcher_path(current_launcher: PathBuf) -> RobloxDirectories {
+        let mut versions = current_launcher.clone();
+        versions.pop();
+        versions.pop();
+        RobloxDirectories { versions, current_launcher }
+    }
+}
+
+/// A running process.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub parent_pid: u32,
+    /// The file name of the executable, such as `RobloxPlayerBeta.exe`.
+    pub name: String,
+}
+
+/// The last component of a Windows or Unix path.
+fn program_file_name(program: &str) -> String {
+    String::from(program.rsplit(['/', '\\']).next().unwrap_or(program))
+}
+
+fn not_found(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, message)
+}
+
+/// Why a command that hands something off to another program, such as Steam, did not succeed.
+#[derive(Debug)]
+pub enum CommandError {
+    /// The command could not be run at all.
+    NotStarted(String, io::Error),
+    /// The command ran but reported failure, so whatever it was handed was not accepted.
+    Failed(String, String),
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CommandError::NotStarted(command, _) => write!(formatter, "Cannot run {}", command),
+            CommandError::Failed(command, status) => write!(formatter, "{} failed: {}", command, status),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CommandError::NotStarted(_, err) => Some(err),
+            CommandError::Failed(..) => None,
+        }
+    }
+}
+
+/// Runs `command` and waits up to `wait` for it to exit. A failure exit status is an error. A
+/// command still running after `wait` is assumed to have accepted the request, as Steam does when
+/// it starts up to handle it.
+pub fn run_command(command: &mut Command, wait: Duration) -> Result<(), CommandError> {
+    let description = format!("{:?}", command);
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(err) => return Err(CommandError::NotStarted(description, err)),
+    };
+    let start = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Ok(()),
+            Ok(Some(status)) => return Err(CommandError::Failed(description, status.to_string())),
+            Ok(None) if start.elapsed() >= wait => return Ok(()),
+            Ok(None) => thread::sleep(Duration::from_millis(50)),
+            Err(err) => return Err(CommandError::NotStarted(description, err)),
+        }
+    }
+}
+
+pub trait Platform {
+    /// Opens `url` with whatever handles it on this system, such as `steam://` URLs with Steam.
+    /// Fails if no handler took the URL.
+    fn open_url(&self, url: &str) -> Result<(), CommandError>;
+
+    /// Reads the string value `name` of the current user's registry key `key`. An empty `name`
+    /// reads the key's default value.
+    fn registry_value(&self, key: &str, name: &str) -> io::Result<String>;
+
+    fn steam_directory(&self) -> io::Result<PathBuf>;
+
+    /// The Steam client executable, which passes its arguments to Steam if it is already running.
+    fn steam_executable(&self) -> io::Result<PathBuf>;
+
+    fn roblox_directories(&self) -> io::Result<RobloxDirectories>;
+
+    /// Every running process.
+    fn processes(&self) -> io::Result<Vec<ProcessInfo>>;
+
+    /// Waits for the process `pid` to exit, whether or not it is our child. Returns its exit code
+    /// if the system can tell.
+    fn wait_for_process(&self, pid: u32) -> io::Result<Option<i32>>;
+}
+
+/// Returns the platform implementation for the running system.
+pub fn current_platform() -> Box<dyn Platform> {
+    #[cfg(windows)]
+    {
+        Box::new(WindowsPlatform)
+    }
+    #[cfg(not(windows))]
+    {
+        Box::new(LinuxPlatform::new())
+    }
+}
+
+#[cfg(windows)]
+pub struct WindowsPlatform;
+
+#[cfg(windows)]
+impl Platform for WindowsPlatform {
+    fn open_url(&self, url: &str) -> Result<(), CommandError> {
+        // `explorer.exe` exits with 1 whether or not the URL was opened, but `start` reports
+        // failure when no program handles the URL.
+        run_command(Command::new("cmd").args(&["/C", "start", ""]).arg(url), COMMAND_ACCEPT_WAIT)
+    }
+
+    fn registry_value(&self, key: &str, name: &str) -> io::Result<String> {
+        use winreg::RegKey;
+        use winreg::enums::*;
+
+        let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+        hkcu.open_subkey_with_flags(key, KEY_READ)?.get_value(name)
+    }
+
+    fn steam_directory(&self) -> io::Result<PathBuf> {
+        Ok(PathBuf::from(self.registry_value(STEAM_REGISTRY_KEY, "SteamPath")?))
+    }
+
+    fn steam_executable(&self) -> io::Result<PathBuf> {
+        Ok(PathBuf::from(self.registry_value(STEAM_REGISTRY_KEY, "SteamExe")?))
+    }
+
+    fn roblox_directories(&self) -> io::Result<RobloxDirectories> {
+        Ok(RobloxDirectories::from_launcher_path(PathBuf::from(self.registry_value(ROBLOX_REGISTRY_KEY, "")?)))
+    }
+
+    fn processes(&self) -> io::Result<Vec<ProcessInfo>> {
+        windows_processes::list()
+    }
+
+    fn wait_for_process(&self, pid: u32) -> io::Result<Option<i32>> {
+        windows_processes::wait(pid).map(Some)
+    }
+}
+
+/// The Toolhelp and process functions from kernel32, which Wine provides too.
+#[cfg(windows)]
+mod windows_processes {
+    use super::{program_file_name, ProcessInfo};
+
+    use std::io;
+    use std::mem;
+    use std::os::raw::c_void;
+
+    type Handle = *mut c_void;
+
+    const INVALID_HANDLE_VALUE: Handle = -1isize as Handle;
+    const TH32CS_SNAPPROCESS: u32 = 0x0000_0002;
+    const SYNCHRONIZE: u32 = 0x0010_0000;
+    const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x0000_1000;
+    const INFINITE: u32 = 0xFFFF_FFFF;
+    const WAIT_FAILED: u32 = 0xFFFF_FFFF;
+    const MAX_PATH: usize = 260;
+
+    #[repr(C)]
+    struct ProcessEntry32W {
+        size: u32,
+        usage: u32,
+        process_id: u32,
+        default_heap_id: usize,
+        module_id: u32,
+        threads: u32,
+        parent_process_id: u32,
+        priority_class_base: i32,
+        flags: u32,
+        exe_file: [u16; MAX_PATH],
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn CreateToolhelp32Snapshot(flags: u32, process_id: u32) -> Handle;
+        fn Process32FirstW(snapshot: Handle, entry: *mut ProcessEntry32W) -> i32;
+        fn Process32NextW(snapshot: Handle, entry: *mut ProcessEntry32W) -> i32;
+        fn OpenProcess(desired_access: u32, inherit_handle: i32, process_id: u32) -> Handle;
+        fn WaitForSingleObject(handle: Handle, milliseconds: u32) -> u32;
+        fn GetExitCodeProcess(process: Handle, exit_code: *mut u32) -> i32;
+        fn CloseHandle(handle: Handle) -> i32;
+    }
+
+    /// Closes the handle when dropped.
+    struct OwnedHandle(Handle);
+
+    impl Drop for OwnedHandle {
+        fn drop(&mut self) {
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+
+    pub fn list() -> io::Result<Vec<ProcessInfo>> {
+        let snapshot = unsafe { CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0) };
+        if snapshot == INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+        let snapshot = OwnedHandle(snapshot);
+        let mut entry: ProcessEntry32W = unsafe { mem::zeroed() };
+        entry.size = mem::size_of::<ProcessEntry32W>() as u32;
+        let mut processes = vec![];
+        let mut found = unsafe { Process32FirstW(snapshot.0, &mut entry) };
+        while found != 0 {
+            let length = entry.exe_file.iter().position(|&c| c == 0).unwrap_or(MAX_PATH);
+            processes.push(ProcessInfo {
+                pid: entry.process_id,
+                parent_pid: entry.parent_process_id,
+                name: program_file_name(&String::from_utf16_lossy(&entry.exe_file[..length])),
+            });
+            found = unsafe { Process32NextW(snapshot.0, &mut entry) };
+        }
+        Ok(processes)
+    }
+
+    pub fn wait(pid: u32) -> io::Result<i32> {
+        let process = unsafe { OpenProcess(SYNCHRONIZE | PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+        if process.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let process = OwnedHandle(process);
+        if unsafe { WaitForSingleObject(process.0, INFINITE) } == WAIT_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        let mut exit_code = 0;
+        if unsafe { GetExitCodeProcess(process.0, &mut exit_code) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // Windows exit codes are unsigned, and crashes use the high bit, such as 0xC0000005.
+        Ok(exit_code as i32)
+    }
+}
+
+/// Reads one process from its directory in `/proc`. Returns `None` if it has exited, which it
+/// may do while it is being read.
+fn read_proc_process(directory: &Path, pid: u32) -> Option<ProcessInfo> {
+    let stat = fs::read_to_string(directory.join("stat")).ok()?;
+    // The command is in parentheses and may itself contain spaces and parentheses.
+    let (open, close) = (stat.find('(')?, stat.rfind(')')?);
+    if close < open {
+        return None;
+    }
+    let mut fields = stat[close + 1..].split_whitespace();
+    if fields.next()? == "Z" {
+        return None;
+    }
+    let parent_pid = fields.next()?.parse().ok()?;
+    // The command is cut to 15 characters, so the program on the command line is used when there
+    // is one. For Wine processes that is the Windows path of the executable.
+    let program = fs::read(directory.join("cmdline")).ok()
+        .and_then(|cmdline| cmdline.split(|&byte| byte == 0).next().filter(|program| !program.is_empty()).map(|program| String::from_utf8_lossy(program).into_owned()));
+    let name = match program {
+        Some(program) => program_file_name(&program),
+        None => String::from(&stat[open + 1..close]),
+    };
+    Some(ProcessInfo { pid, parent_pid, name })
+}
+
+/// Lists the processes in a `/proc` directory.
+pub fn read_proc_processes(proc_path: &Path) -> io::Result<Vec<ProcessInfo>> {
+    let mut processes = vec![];
+    for entry in fs::read_dir(proc_path)? {
+        let entry = entry?;
+        let pid = match entry.file_name().to_str().and_then(|name| name.parse().ok()) {
+            Some(pid) => pid,
+            None => continue,
+        };
+        if let Some(process) = read_proc_process(&entry.path(), pid) {
+            processes.push(process);
+        }
+    }
+    Ok(processes)
+}
+
+/// Reads a string value from a Wine registry file such as `user.reg`.
+pub fn read_wine_registry_value(registry: &str, key: &str, name: &str) -> Option<String> {
+    let header = format!("[{}]", key.replace('\\', "\\\\"));
+    let wanted = if name.is_empty() { String::from("@") } else { format!("\"{}\"", name) };
+    let mut in_key = false;
+    for line in registry.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            // Headers are followed by a timestamp, as in `[Software\\RobloxReg] 1500000000`.
+            in_key = line.split(']').next().map(|header_key| format!("{}]", header_key).eq_ignore_ascii_case(&header)).unwrap_or(false);
+            continue;
+        }
+        if !in_key {
+            continue;
+        }
+        let (value_name, value) = match line.find('=') {
+            Some(index) => (&line[..index], &line[index + 1..]),
+            None => continue,
+        };
+        if !value_name.eq_ignore_ascii_case(&wanted) || !value.starts_with('"') || !value.ends_with('"') || value.len() < 2 {
+            continue;
+        }
+        let mut unescaped = String::new();
+        let mut chars = value[1..value.len() - 1].chars();
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                if let Some(escaped) = chars.next() {
+                    unescaped.push(escaped);
+                }
+            } else {
+                unescaped.push(c);
+            }
+        }
+        return Some(unescaped);
+    }
+    None
+}
+
+/// Runs natively on Linux, with Roblox in a Wine prefix and Steam installed natively.
+pub struct LinuxPlatform {
+    pub home: PathBuf,
+    pub wine_prefix: PathBuf,
+}
+
+impl LinuxPlatform {
+    /// Uses `$HOME` and `$WINEPREFIX`, defaulting to `~/.wine`.
+    pub fn new() -> LinuxPlatform {
+        let home = env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+        let wine_prefix = env::var_os("WINEPREFIX").map(PathBuf::from).unwrap_or_else(|| home.join(".wine"));
+        LinuxPlatform { home, wine_prefix }
+    }
+
+    /// Maps a Windows path inside the Wine prefix, such as `C:\users\...`, to a Linux path.
+    pub fn wine_path_to_linux(&self, windows_path: &str) -> PathBuf {
+        let mut parts = windows_path.split('\\');
+        let mut path = match parts.next() {
+            Some(drive) if drive.len() == 2 && drive.ends_with(':') => self.wine_prefix.join(format!("drive_{}", drive[..1].to_lowercase())),
+            Some(first) => self.wine_prefix.join("drive_c").join(first),
+            None => self.wine_prefix.join("drive_c"),
+        };
+        for part in parts.filter(|part| !part.is_empty()) {
+            path.push(part);
+        }
+        path
+    }
+
+    /// Looks for `Versions` in each Wine user's profile when the registry doesn't say.
+    fn find_versions_directory(&self) -> Option<PathBuf> {
+        let users = fs::read_dir(self.wine_prefix.join("drive_c").join("users")).ok()?;
+        users.filter_map(|user| user.ok())
+            .map(|user| user.path().join("AppData").join("Local").join("Roblox").join("Versions"))
+            .find(|versions| versions.is_dir())
+    }
+}
+
+impl Default for LinuxPlatform {
+    fn default() -> LinuxPlatform {
+        LinuxPlatform::new()
+    }
+}
+
+impl Platform for LinuxPlatform {
+    fn open_url(&self, url: &str) -> Result<(), CommandError> {
+        run_command(Command::new("xdg-open").arg(url), COMMAND_ACCEPT_WAIT)
+    }
+
+    fn registry_value(&self, key: &str, name: &str) -> io::Result<String> {
+        let registry = fs::read_to_string(self.wine_prefix.join("user.reg"))?;
+        match read_wine_registry_value(&registry, key, name) {
+            Some(value) => Ok(value),
+            None => Err(not_found(format!("No registry value {:?} in {}", name, key))),
+        }
+    }
+
+    fn steam_directory(&self) -> io::Result<PathBuf> {
+        let candidates = [
+            self.home.join(".steam").join("steam"),
+            self.home.join(".local").join("share").join("Steam"),
+            self.home.join(".var").join("app").join("com.valvesoftware.Steam").join(".local").join("share").join("Steam"),
+        ];
+        match candidates.iter().find(|candidate| candidate.join("userdata").is_dir()) {
+            Some(directory) => Ok(directory.clone()),
+            None => Err(not_found(String::from("Cannot find a Steam installation"))),
+        }
+    }
+
+    fn steam_executable(&self) -> io::Result<PathBuf> {
+        // The `steam` launcher script is on `PATH` for every packaging of the native client.
+        Ok(PathBuf::from("steam"))
+    }
+
+    fn roblox_directories(&self) -> io::Result<RobloxDirectories> {
+        if let Ok(launcher) = self.registry_value(ROBLOX_REGISTRY_KEY, "") {
+            return Ok(RobloxDirectories::from_launcher_path(self.wine_path_to_linux(&launcher)));
+        }
+        let versions = match self.find_versions_directory() {
+            Some(versions) => versions,
+            None => return Err(not_found(format!("Cannot find Roblox in the Wine prefix {}", self.wine_prefix.display()))),
+        };
+        match find_newest_player_version(&versions, VersionOrder::Created) {
+            Ok(Some(version)) => Ok(RobloxDirectories { versions, current_launcher: version.launcher_path() }),
+            Ok(None) => Err(not_found(format!("No Roblox player in {}", versions.display()))),
+            Err(err) => Err(err.error),
+        }
+    }
+
+    fn processes(&self) -> io::Result<Vec<ProcessInfo>> {
+        read_proc_processes(Path::new("/proc"))
+    }
+
+    /// Linux only tells a process's parent how it exited, so the exit code is unknown.
+    fn wait_for_process(&self, pid: u32) -> io::Result<Option<i32>> {
+        let directory = Path::new("/proc").join(pid.to_string());
+        while read_proc_process(&directory, pid).is_some() {
+            thread::sleep(PROCESS_POLL_INTERVAL);
+        }
+        Ok(None)
+    }
+}
+
+/// An in-memory platform for tests. URLs are recorded instead of opened.
+#[derive(Default)]
+pub struct FakePlatform {
+    pub registry: HashMap<(String, String), String>,
+    pub steam_directory: Option<PathBuf>,
+    pub steam_executable: Option<PathBuf>,
+    /// Makes `open_url` fail as if no program handled the URL. URLs are still recorded.
+    pub rejects_urls: bool,
+    pub roblox_launcher: Option<PathBuf>,
+    pub processes: Vec<ProcessInfo>,
+    /// What `wait_for_process` returns for each process. Others exited with an unknown code.
+    pub exit_codes: HashMap<u32, i32>,
+    opened_urls: Mutex<Vec<String>>,
+}
+
+impl FakePlatform {
+    pub fn new() -> FakePlatform {
+        FakePlatform::default()
+    }
+
+    pub fn set_registry_value(&mut self, key: &str, name: &str, value: &str) {
+        self.registry.insert((key.to_lowercase(), name.to_lowercase()), String::from(value));
+    }
+
+    /// Every URL passed to `open_url` so far, oldest first.
+    pub fn opened_urls(&self) -> Vec<String> {
+        match self.opened_urls.lock() {
+            Ok(urls) => urls.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+}
+
+impl Platform for FakePlatform {
+    fn open_url(&self, url: &str) -> Result<(), CommandError> {
+        match self.opened_urls.lock() {
+            Ok(mut urls) => urls.push(String::from(url)),
+            Err(poisoned) => poisoned.into_inner().push(String::from(url)),
+        }
+        if self.rejects_urls {
+            return Err(CommandError::Failed(format!("open {}", url), String::from("no handler")));
+        }
+        Ok(())
+    }
+
+    fn registry_value(&self, key: &str, name: &str) -> io::Result<String> {
+        match self.registry.get(&(key.to_lowercase(), name.to_lowercase())) {
+            Some(value) => Ok(value.clone()),
+            None => Err(not_found(format!("No registry value {:?} in {}", name, key))),
+        }
+    }
+
+    fn steam_directory(&self) -> io::Result<PathBuf> {
+        match self.steam_directory {
+            Some(ref directory) => Ok(directory.clone()),
+            None => Err(not_found(String::from("No Steam directory"))),
+        }
+    }
+
+    fn steam_executable(&self) -> io::Result<PathBuf> {
+        match self.steam_executable {
+            Some(ref executable) => Ok(executable.clone()),
+            None => Err(not_found(String::from("No Steam executable"))),
+        }
+    }
+
+    fn roblox_directories(&self) -> io::Result<RobloxDirectories> {
+        match self.roblox_launcher {
+            Some(ref launcher) => Ok(RobloxDirectories::from_launcher_path(launcher.clone())),
+            None => Err(not_found(String::from("No Roblox installation"))),
+        }
+    }
+
+    fn processes(&self) -> io::Result<Vec<ProcessInfo>> {
+        Ok(self.processes.clone())
+    }
+
+    fn wait_for_process(&self, pid: u32) -> io::Result<Option<i32>> {
+        Ok(self.exit_codes.get(&pid).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER_REG: &str = r#"WINE REGISTRY Version 2
+;; All keys relative to \\User\\S-1-5-21-0-0-0-1000
+
+[Software\\RobloxReg] 1500000000
+#time=1d2f8a1b2c3d4e5
+@="C:\\users\\steamuser\\AppData\\Local\\Roblox\\Versions\\version-abc\\RobloxPlayerLauncher.exe"
+
+[Software\\Valve\\Steam] 1500000000
+"SteamPath"="c:/program files (x86)/steam"
+"Quoted"="say \"hi\""
+"#;
+
+    #[test]
+    fn reads_wine_registry() {
+        assert_eq!(read_wine_registry_value(USER_REG, ROBLOX_REGISTRY_KEY, ""), Some(String::from("C:\\users\\steamuser\\AppData\\Local\\Roblox\\Versions\\version-abc\\RobloxPlayerLauncher.exe")));
+        assert_eq!(read_wine_registry_value(USER_REG, STEAM_REGISTRY_KEY, "SteamPath"), Some(String::from("c:/program files (x86)/steam")));
+        assert_eq!(read_wine_registry_value(USER_REG, STEAM_REGISTRY_KEY, "Quoted"), Some(String::from("say \"hi\"")));
+        assert_eq!(read_wine_registry_value(USER_REG, STEAM_REGISTRY_KEY, ""), None);
+        assert_eq!(read_wine_registry_value(USER_REG, "Software\\Missing", ""), None);
+    }
+
+    #[test]
+    fn maps_wine_paths() {
+        let platform = LinuxPlatform { home: PathBuf::from("/home/me"), wine_prefix: PathBuf::from("/home/me/.wine") };
+        let directories = RobloxDirectories::from_launcher_path(platform.wine_path_to_linux("C:\\users\\me\\AppData\\Local\\Roblox\\Versions\\version-abc\\RobloxPlayerLauncher.exe"));
+        assert_eq!(directories.versions, PathBuf::from("/home/me/.wine/drive_c/users/me/AppData/Local/Roblox/Versions"));
+        assert_eq!(directories.current_launcher, PathBuf::from("/home/me/.wine/drive_c/users/me/AppData/Local/Roblox/Versions/version-abc/RobloxPlayerLauncher.exe"));
+    }
+
+    #[test]
+    fn fake_platform_records_urls() {
+        let mut platform = FakePlatform::new();
+        platform.set_registry_value(STEAM_REGISTRY_KEY, "SteamPath", "C:/Steam");
+        assert_eq!(platform.registry_value("software\\valve\\steam", "steampath").unwrap(), "C:/Steam");
+        assert!(platform.steam_directory().is_err());
+        platform.open_url("steam://rungameid/1").unwrap();
+        assert_eq!(platform.opened_urls(), vec![String::from("steam://rungameid/1")]);
+    }
+
+    #[test]
+    fn reads_proc_processes() {
+        let proc_path = std::env::temp_dir().join(format!("roblox_steam_launcher_platform_test_{}_proc", std::process::id()));
+        let write = |pid: &str, stat: &str, cmdline: &[u8]| {
+            fs::create_dir_all(proc_path.join(pid)).unwrap();
+            fs::write(proc_path.join(pid).join("stat"), stat).unwrap();
+            fs::write(proc_path.join(pid).join("cmdline"), cmdline).unwrap();
+        };
+        write("1", "1 (systemd) S 0 1 1", b"/sbin/init\0splash\0");
+        write("42", "42 (RobloxPlayerBet) S 7 42 42", b"C:\\users\\me\\RobloxPlayerBeta.exe\0--play\0");
+        write("43", "43 (kworker (1)) S 2 0 0", b"");
+        write("44", "44 (defunct) Z 1 0 0", b"");
+        fs::create_dir_all(proc_path.join("self")).unwrap();
+
+        let mut processes = read_proc_processes(&proc_path).unwrap();
+        processes.sort_by_key(|process| process.pid);
+        assert_eq!(processes, vec![
+            ProcessInfo { pid: 1, parent_pid: 0, name: String::from("init") },
+            ProcessInfo { pid: 42, parent_pid: 7, name: String::from("RobloxPlayerBeta.exe") },
+            ProcessInfo { pid: 43, parent_pid: 2, name: String::from("kworker (1)") },
+        ]);
+        fs::remove_dir_all(&proc_path).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn lists_linux_processes() {
+        let processes = LinuxPlatform::new().processes().unwrap();
+        assert!(processes.iter().any(|process| process.pid == std::process::id()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn run_command_reports_exit_status() {
+        assert!(run_command(&mut Command::new("true"), COMMAND_ACCEPT_WAIT).is_ok());
+        match run_command(&mut Command::new("false"), COMMAND_ACCEPT_WAIT) {
+            Err(CommandError::Failed(..)) => (),
+            other => panic!("expected CommandError::Failed, got {:?}", other),
+        }
+        match run_command(&mut Command::new("roblox_steam_launcher_no_such_program"), COMMAND_ACCEPT_WAIT) {
+            Err(CommandError::NotStarted(..)) => (),
+            other => panic!("expected CommandError::NotStarted, got {:?}", other),
+        }
+        // Still running after the wait counts as accepted.
+        assert!(run_command(Command::new("sleep").arg("1"), Duration::from_millis(100)).is_ok());
+    }
+}
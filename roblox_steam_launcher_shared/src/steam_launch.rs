@@ -0,0 +1,176 @@
+//! The ways the substitute can ask Steam to start the master, and how either of them can tell
+//! whether Steam started it.
+
+use platform::{run_command, CommandError, Platform, COMMAND_ACCEPT_WAIT};
+use unix_time_now;
+use write_file_atomically;
+
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// Environment variables Steam sets for the programs it starts, including non-Steam shortcuts.
+pub const STEAM_ENVIRONMENT_VARIABLES: &[&str] = &["SteamAppId", "SteamGameId", "SteamClientLaunch"];
+
+/// Whether this process was started by Steam.
+pub fn started_by_steam() -> bool {
+    started_by_steam_in(|name| env::var_os(name))
+}
+
+fn started_by_steam_in<F: Fn(&str) -> Option<OsString>>(lookup: F) -> bool {
+    STEAM_ENVIRONMENT_VARIABLES.iter().any(|name| match lookup(name) {
+        Some(value) => !value.is_empty(),
+        None => false,
+    })
+}
+
+pub fn get_bounce_file_name() -> &'static str {
+    "roblox_steam_launcher_bounce"
+}
+
+fn get_bounce_path(directory: &Path) -> PathBuf {
+    directory.join(get_bounce_file_name())
+}
+
+/// Records in `directory` that the master is restarting itself through Steam. Returns false, and
+/// clears the record, if it already did so within `window`: Steam started it again without
+/// identifying itself, and restarting once more would loop.
+pub fn begin_bounce(directory: &Path, window: Duration) -> io::Result<bool> {
+    let path = get_bounce_path(directory);
+    let now = unix_time_now();
+    if let Ok(contents) = fs::read_to_string(&path) {
+        if let Ok(time) = contents.trim().parse::<u64>() {
+            if now.saturating_sub(time) <= window.as_secs() {
+                fs::remove_file(&path)?;
+                return Ok(false);
+            }
+        }
+    }
+    write_file_atomically(&path, now.to_string().as_bytes())?;
+    Ok(true)
+}
+
+/// Clears the record left by `begin_bounce` once Steam has started the master.
+pub fn end_bounce(directory: &Path) -> io::Result<()> {
+    match fs::remove_file(get_bounce_path(directory)) {
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// How the substitute asks Steam to run the master's shortcut. Stored in the config under
+/// `steam_launch`, tagged by `strategy`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum SteamLaunchStrategy {
+    /// Opens `steam://rungameid/{gameid}` with the system's URL handler.
+    #[default]
+    Url,
+    /// Runs the Steam client with `steam://rungameid/{gameid}`. `path` overrides where the Steam
+    /// client is found.
+    SteamBinary {
+        #[serde(default)]
+        path: Option<String>,
+    },
+    /// Runs `program` with `arguments`, replacing `{gameid}` in each of them.
+    Command {
+        program: String,
+        #[serde(default)]
+        arguments: Vec<String>,
+    },
+}
+
+fn fill_template(template: &str, game_id: &str) -> String {
+    template.replace("{gameid}", game_id)
+}
+
+impl SteamLaunchStrategy {
+    /// Asks Steam to run the shortcut `game_id`. Succeeds only if the request was accepted.
+    pub fn launch(&self, platform: &dyn Platform, game_id: &str) -> Result<(), CommandError> {
+        let url = format!("steam://rungameid/{}", game_id);
+        match self {
+            SteamLaunchStrategy::Url => platform.open_url(&url),
+            SteamLaunchStrategy::SteamBinary { path } => {
+                let executable = match path {
+                    Some(path) => path.into(),
+                    None => match platform.steam_executable() {
+                        Ok(executable) => executable,
+                        Err(err) => return Err(CommandError::NotStarted(String::from("Steam"), err)),
+                    },
+                };
+                let mut command = Command::new(executable);
+                command.arg(&url);
+                run_command(&mut command, COMMAND_ACCEPT_WAIT)
+            },
+            SteamLaunchStrategy::Command { program, arguments } => {
+                let mut command = Command::new(fill_template(program, game_id));
+                command.args(arguments.iter().map(|argument| fill_template(argument, game_id)));
+                run_command(&mut command, COMMAND_ACCEPT_WAIT)
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use platform::FakePlatform;
+    use serde_json;
+
+    #[test]
+    fn url_strategy_opens_rungameid() {
+        let mut platform = FakePlatform::new();
+        assert!(SteamLaunchStrategy::Url.launch(&platform, "123").is_ok());
+        platform.rejects_urls = true;
+        assert!(SteamLaunchStrategy::Url.launch(&platform, "456").is_err());
+        assert_eq!(platform.opened_urls(), vec![String::from("steam://rungameid/123"), String::from("steam://rungameid/456")]);
+    }
+
+    #[test]
+    fn strategies_round_trip_through_json() {
+        let strategy: SteamLaunchStrategy = serde_json::from_str(r#"{"strategy":"command","program":"steam","arguments":["-applaunch","{gameid}"]}"#).unwrap();
+        assert_eq!(strategy, SteamLaunchStrategy::Command { program: String::from("steam"), arguments: vec![String::from("-applaunch"), String::from("{gameid}")] });
+        let strategy: SteamLaunchStrategy = serde_json::from_str(r#"{"strategy":"steam_binary"}"#).unwrap();
+        assert_eq!(strategy, SteamLaunchStrategy::SteamBinary { path: None });
+        assert_eq!(serde_json::to_string(&SteamLaunchStrategy::Url).unwrap(), r#"{"strategy":"url"}"#);
+    }
+
+    #[test]
+    fn detects_steam_environment() {
+        assert!(!started_by_steam_in(|_| None));
+        assert!(!started_by_steam_in(|_| Some(OsString::new())));
+        assert!(started_by_steam_in(|name| if name == "SteamGameId" { Some(OsString::from("123")) } else { None }));
+    }
+
+    #[test]
+    fn bounces_only_once() {
+        let dir = env::temp_dir().join(format!("roblox_steam_launcher_bounce_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let window = Duration::from_secs(60);
+        assert!(begin_bounce(&dir, window).unwrap());
+        assert!(!begin_bounce(&dir, window).unwrap());
+        assert!(begin_bounce(&dir, window).unwrap());
+        end_bounce(&dir).unwrap();
+        assert!(!get_bounce_path(&dir).exists());
+        end_bounce(&dir).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn command_strategy_reports_exit_status() {
+        let platform = FakePlatform::new();
+        let accepted = SteamLaunchStrategy::Command { program: String::from("sh"), arguments: vec![String::from("-c"), String::from("test {gameid} = 123")] };
+        assert!(accepted.launch(&platform, "123").is_ok());
+        assert!(accepted.launch(&platform, "456").is_err());
+        let missing = SteamLaunchStrategy::SteamBinary { path: None };
+        match missing.launch(&platform, "123") {
+            Err(CommandError::NotStarted(..)) => (),
+            other => panic!("expected CommandError::NotStarted, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Keeps the master running for as long as the Roblox client does, so Steam shows the game as
+//! running, counts playtime, and keeps the overlay attached for the whole session.
+//!
+//! The launcher the master starts only starts the client and exits, so the client is found by
+//! following the process tree down from the launcher. Everything seen in the tree is remembered,
+//! because once the launcher exits its children may be given a new parent.
+
+use platform::{Platform, ProcessInfo};
+
+use std::collections::HashSet;
+use std::io;
+use std::process::Child;
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const GAME_CLIENT_FILE_NAME: &str = "RobloxPlayerBeta.exe";
+
+/// How long the client may take to appear after the launcher has exited before the launcher is
+/// assumed not to have started one, such as when it only opened the app.
+pub const CLIENT_START_GRACE: Duration = Duration::from_secs(30);
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How a supervised launch ended.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Supervised {
+    /// The client ran and exited, with this code if the system could tell.
+    Client { pid: u32, exit_code: Option<i32> },
+    /// The launcher exited without starting a client.
+    LauncherOnly { exit_code: Option<i32> },
+}
+
+impl Supervised {
+    /// The exit code to pass on, which is success if it is unknown.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Supervised::Client { exit_code, .. } | Supervised::LauncherOnly { exit_code } => exit_code.unwrap_or(0),
+        }
+    }
+}
+
+/// The processes seen descending from one root process.
+pub struct ProcessTree {
+    name: String,
+    members: HashSet<u32>,
+    preexisting: HashSet<u32>,
+}
+
+impl ProcessTree {
+    /// Follows the tree below `root` looking for a process named `name`. `existing` are the
+    /// processes that were running before `root` was started.
+    pub fn new(root: u32, name: &str, existing: &[ProcessInfo]) -> ProcessTree {
+        let mut members = HashSet::new();
+        members.insert(root);
+        ProcessTree {
+            name: name.to_lowercase(),
+            members,
+            preexisting: existing.iter().map(|process| process.pid).collect(),
+        }
+    }
+
+    /// Adds the processes in `processes` that descend from the tree, and returns the one named
+    /// `name`. If none of them is, a process named `name` that wasn't running before the root is
+    /// returned instead, since it may have lost its parent before it was seen.
+    pub fn observe(&mut self, processes: &[ProcessInfo]) -> Option<u32> {
+        // A child may be listed before its parent, so repeat until nothing more is added.
+        loop {
+            let before = self.members.len();
+            for process in processes {
+                if self.members.contains(&process.parent_pid) && !self.preexisting.contains(&process.pid) {
+                    self.members.insert(process.pid);
+                }
+            }
+            if self.members.len() == before {
+                break;
+            }
+        }
+        let named = |process: &&ProcessInfo| process.name.to_lowercase() == self.name;
+        processes.iter().filter(named).find(|process| self.members.contains(&process.pid))
+            .or_else(|| processes.iter().filter(named).find(|process| !self.preexisting.contains(&process.pid)))
+            .map(|process| process.pid)
+    }
+}
+
+/// Waits for the client started by `launcher` to exit. `existing` are the processes that were
+/// running before `launcher` was started.
+pub fn supervise(platform: &dyn Platform, launcher: &mut Child, existing: &[ProcessInfo]) -> io::Result<Supervised> {
+    let mut tree = ProcessTree::new(launcher.id(), GAME_CLIENT_FILE_NAME, existing);
+    let mut launcher_exit: Option<(Option<i32>, Instant)> = None;
+    let client = loop {
+        if let Some(pid) = tree.observe(&platform.processes()?) {
+            break pid;
+        }
+        match launcher_exit {
+            Some((exit_code, exited)) if exited.elapsed() >= CLIENT_START_GRACE => {
+                log_info!("The launcher exited without starting {}", GAME_CLIENT_FILE_NAME);
+                return Ok(Supervised::LauncherOnly { exit_code });
+            },
+            Some(_) => {},
+            None => if let Some(status) = launcher.try_wait()? {
+                log_debug!("The launcher exited with {}", status);
+                launcher_exit = Some((status.code(), Instant::now()));
+            },
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    log_info!("Supervising {} (process {})", GAME_CLIENT_FILE_NAME, client);
+    let exit_code = platform.wait_for_process(client)?;
+    match exit_code {
+        Some(exit_code) => log_info!("{} exited with code {}", GAME_CLIENT_FILE_NAME, exit_code),
+        None => log_info!("{} exited", GAME_CLIENT_FILE_NAME),
+    }
+    // Reap the launcher if it is still around.
+    let _ = launcher.try_wait();
+    Ok(Supervised::Client { pid: client, exit_code })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use platform::FakePlatform;
+    use std::process::Command;
+
+    fn process(pid: u32, parent_pid: u32, name: &str) -> ProcessInfo {
+        ProcessInfo { pid, parent_pid, name: String::from(name) }
+    }
+
+    #[test]
+    fn follows_process_tree_to_client() {
+        let existing = vec![process(1, 0, "init"), process(5, 1, "RobloxPlayerBeta.exe")];
+        let mut tree = ProcessTree::new(10, GAME_CLIENT_FILE_NAME, &existing);
+
+        // The client running from before the launch is not the one started by it.
+        assert_eq!(tree.observe(&[process(1, 0, "init"), process(5, 1, "RobloxPlayerBeta.exe"), process(10, 1, "RobloxPlayerLauncher.exe")]), None);
+        // Grandchildren listed before their parents are still found.
+        assert_eq!(tree.observe(&[
+            process(5, 1, "RobloxPlayerBeta.exe"),
+            process(12, 11, "robloxplayerbeta.exe"),
+            process(11, 10, "RobloxCrashHandler.exe"),
+            process(10, 1, "RobloxPlayerLauncher.exe"),
+        ]), Some(12));
+        // Once the launcher has exited, its descendants are still known.
+        assert_eq!(tree.observe(&[process(5, 1, "RobloxPlayerBeta.exe"), process(12, 1, "RobloxPlayerBeta.exe")]), Some(12));
+
+        // A new client that was given a new parent before it was seen is found too.
+        let mut tree = ProcessTree::new(20, GAME_CLIENT_FILE_NAME, &existing);
+        assert_eq!(tree.observe(&[process(5, 1, "RobloxPlayerBeta.exe"), process(21, 1, "RobloxPlayerBeta.exe")]), Some(21));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn supervises_client_exit_code() {
+        let mut launcher = Command::new("true").spawn().unwrap();
+        let mut platform = FakePlatform::default();
+        platform.processes = vec![process(launcher.id() + 1, launcher.id(), "RobloxPlayerBeta.exe")];
+        platform.exit_codes.insert(launcher.id() + 1, 3);
+
+        let supervised = supervise(&platform, &mut launcher, &[]).unwrap();
+        assert_eq!(supervised, Supervised::Client { pid: launcher.id() + 1, exit_code: Some(3) });
+        assert_eq!(supervised.exit_code(), 3);
+        assert_eq!(Supervised::LauncherOnly { exit_code: None }.exit_code(), 0);
+    }
+}
@@ -0,0 +1,209 @@
+//! Discovery of the version directories under Roblox's `Versions` directory.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+pub const LAUNCHER_FILE_NAME: &str = "RobloxPlayerLauncher.exe";
+pub const ORIGINAL_LAUNCHER_FILE_NAME: &str = "RobloxPlayerLauncher_original.exe";
+pub const PLAYER_FILE_NAME: &str = "RobloxPlayerBeta.exe";
+
+/// A directory under `Versions`, and what was found in it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionDirectory {
+    pub path: PathBuf,
+    /// The directory name, such as `version-0123456789abcdef`. This is what
+    /// `setup.roblox.com/version` reports.
+    pub version: String,
+    pub has_launcher: bool,
+    pub has_original_launcher: bool,
+    pub has_player: bool,
+    /// Not every filesystem records creation time.
+    pub created: Option<SystemTime>,
+    pub modified: Option<SystemTime>,
+}
+
+impl VersionDirectory {
+    /// Inspects the version directory at `path`.
+    pub fn from_path(path: &Path) -> io::Result<VersionDirectory> {
+        let metadata = fs::metadata(path)?;
+        if !metadata.is_dir() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a directory"));
+        }
+        Ok(VersionDirectory {
+            path: path.to_path_buf(),
+            version: path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default(),
+            has_launcher: path.join(LAUNCHER_FILE_NAME).is_file(),
+            has_original_launcher: path.join(ORIGINAL_LAUNCHER_FILE_NAME).is_file(),
+            has_player: path.join(PLAYER_FILE_NAME).is_file(),
+            created: metadata.created().ok(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    /// The part of the directory name after `version-`.
+    pub fn hash(&self) -> &str {
+        if self.version.starts_with("version-") {
+            &self.version["version-".len()..]
+        } else {
+            &self.version
+        }
+    }
+
+    /// Whether this directory holds a Roblox player, as opposed to Studio or leftovers.
+    pub fn is_player(&self) -> bool {
+        self.has_launcher || self.has_original_launcher
+    }
+
+    /// Whether the launcher has been replaced by the substitute here.
+    pub fn is_applied(&self) -> bool {
+        self.has_original_launcher
+    }
+
+    pub fn launcher_path(&self) -> PathBuf {
+        self.path.join(LAUNCHER_FILE_NAME)
+    }
+
+    pub fn original_launcher_path(&self) -> PathBuf {
+        self.path.join(ORIGINAL_LAUNCHER_FILE_NAME)
+    }
+}
+
+/// How to order discovered version directories. Every order puts the newest directory first, and
+/// ties are broken by name so the result is always the same for the same directories.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VersionOrder {
+    /// By creation time, using modification time where creation time is not recorded.
+    Created,
+    /// By modification time.
+    Modified,
+    /// By directory name, descending.
+    Name,
+}
+
+impl VersionOrder {
+    fn key(self, directory: &VersionDirectory) -> Option<SystemTime> {
+        match self {
+            VersionOrder::Created => directory.created.or(directory.modified),
+            VersionOrder::Modified => directory.modified,
+            VersionOrder::Name => None,
+        }
+    }
+
+    fn compare(self, a: &VersionDirectory, b: &VersionDirectory) -> Ordering {
+        // Directories without a timestamp sort last.
+        self.key(b).cmp(&self.key(a)).then_with(|| b.version.cmp(&a.version))
+    }
+}
+
+#[derive(Debug)]
+pub struct VersionsError {
+    pub path: PathBuf,
+    pub error: io::Error,
+}
+
+impl fmt::Display for VersionsError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "Cannot read Roblox versions directory {}", self.path.display())
+    }
+}
+
+impl std::error::Error for VersionsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Returns every directory under `versions_path`, newest first according to `order`.
+pub fn discover_versions(versions_path: &Path, order: VersionOrder) -> Result<Vec<VersionDirectory>, VersionsError> {
+    let entries = match versions_path.read_dir() {
+        Ok(entries) => entries,
+        Err(error) => return Err(VersionsError { path: versions_path.to_path_buf(), error }),
+    };
+    let mut directories = vec![];
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error) => return Err(VersionsError { path: versions_path.to_path_buf(), error }),
+        };
+        // Entries that vanish or turn out not to be directories are skipped.
+        if let Ok(directory) = VersionDirectory::from_path(&entry.path()) {
+            directories.push(directory);
+        }
+    }
+    directories.sort_by(|a, b| order.compare(a, b));
+    Ok(directories)
+}
+
+/// Returns the newest directory holding a Roblox player, according to `order`.
+pub fn find_newest_player_version(versions_path: &Path, order: VersionOrder) -> Result<Option<VersionDirectory>, VersionsError> {
+    Ok(discover_versions(versions_path, order)?.into_iter().find(|directory| directory.is_player()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("roblox_steam_launcher_versions_test_{}_{}", std::process::id(), name));
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    fn make_version(versions: &Path, name: &str, files: &[&str]) {
+        let path = versions.join(name);
+        fs::create_dir_all(&path).unwrap();
+        for file in files {
+            fs::write(path.join(file), "").unwrap();
+        }
+    }
+
+    #[test]
+    fn discovers_contents() {
+        let versions = test_dir("contents");
+        make_version(&versions, "version-aaa", &[LAUNCHER_FILE_NAME, PLAYER_FILE_NAME]);
+        make_version(&versions, "version-bbb", &[LAUNCHER_FILE_NAME, ORIGINAL_LAUNCHER_FILE_NAME]);
+        make_version(&versions, "version-ccc", &["RobloxStudioBeta.exe"]);
+        fs::write(versions.join("roblox_steam_launcher_config.json"), "{}").unwrap();
+
+        let found = discover_versions(&versions, VersionOrder::Name).unwrap();
+        let names: Vec<_> = found.iter().map(|directory| directory.version.as_str()).collect();
+        assert_eq!(names, vec!["version-ccc", "version-bbb", "version-aaa"]);
+        assert!(!found[0].is_player());
+        assert!(found[1].is_applied());
+        assert_eq!(found[1].hash(), "bbb");
+        assert!(found[2].has_player && !found[2].is_applied());
+
+        let newest = find_newest_player_version(&versions, VersionOrder::Name).unwrap().unwrap();
+        assert_eq!(newest.version, "version-bbb");
+
+        fs::remove_dir_all(&versions).unwrap();
+    }
+
+    #[test]
+    fn timestamp_ties_are_broken_by_name() {
+        let time = SystemTime::now();
+        let directory = |name: &str| VersionDirectory {
+            path: PathBuf::from(name),
+            version: String::from(name),
+            has_launcher: true,
+            has_original_launcher: false,
+            has_player: true,
+            created: None,
+            modified: Some(time),
+        };
+        let (a, b) = (directory("version-a"), directory("version-b"));
+        assert_eq!(VersionOrder::Created.compare(&a, &b), Ordering::Greater);
+        assert_eq!(VersionOrder::Modified.compare(&b, &a), Ordering::Less);
+    }
+
+    #[test]
+    fn missing_directory_is_an_error() {
+        let root = test_dir("missing");
+        assert!(discover_versions(&root.join("Versions"), VersionOrder::Created).is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,27 +1,196 @@
 extern crate roblox_steam_launcher_shared;
 
 use roblox_steam_launcher_shared::*;
+use roblox_steam_launcher_shared::capabilities::print_if_requested;
+use roblox_steam_launcher_shared::error::describe;
+use roblox_steam_launcher_shared::fallback::FallbackRecord;
+use roblox_steam_launcher_shared::install::{SUBSTITUTE_MARKER, SUBSTITUTE_VERSION_TAG};
+use roblox_steam_launcher_shared::ipc::*;
+use roblox_steam_launcher_shared::launch_uri::{check_arguments, LaunchArgument};
+use roblox_steam_launcher_shared::logging;
+use roblox_steam_launcher_shared::logging::Level;
+use roblox_steam_launcher_shared::platform::{current_platform, Platform};
+use roblox_steam_launcher_shared::steam_launch::started_by_steam;
+use roblox_steam_launcher_shared::versions::ORIGINAL_LAUNCHER_FILE_NAME;
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Lets the master and installer recognise this executable whatever it is named.
+#[used]
+static EMBEDDED_SUBSTITUTE_MARKER: &[u8] = SUBSTITUTE_MARKER;
+
+/// Lets `apply` tell whether this build is older than the one it is installing.
+#[used]
+static EMBEDDED_SUBSTITUTE_VERSION: &[u8] = SUBSTITUTE_VERSION_TAG;
 
 fn main() {
+    // Reading the marker and version keeps the linker from discarding them.
+    let _ = unsafe { std::ptr::read_volatile(&EMBEDDED_SUBSTITUTE_MARKER) };
+    let _ = unsafe { std::ptr::read_volatile(&EMBEDDED_SUBSTITUTE_VERSION) };
+    if print_if_requested("roblox_steam_launcher_substitute", env!("CARGO_PKG_VERSION"), &get_intended_arguments(&mut std::env::args())) {
+        return;
+    }
     let program_dir = match get_program_directory(&mut std::env::args()) {
         Some(path) => path,
         None => panic!("Cannot get program directory."),
     };
-    let mut config_path = program_dir.clone();
-    config_path.pop();  // up from a `version-###` folder to `versions`
-    config_path.push(get_config_file_name());  // down to config file
-    let mut config = match Config::from_path(&config_path) {
+    let mut versions_dir = program_dir.clone();
+    versions_dir.pop();  // up from a `version-###` folder to `versions`
+    // There is no console to read once Roblox has started, so record what happens.
+    logging::init("substitute", &versions_dir, Level::Info);
+    logging::log_panics();
+    run(&*current_platform(), &program_dir, &versions_dir, get_intended_arguments(&mut std::env::args()));
+}
+
+/// Hands `arguments` to the master through Steam, or launches Roblox with them if the master
+/// can't be reached. `program_dir` is the version directory we are in, under `versions_dir`.
+fn run(platform: &dyn Platform, program_dir: &Path, versions_dir: &Path, arguments: Vec<String>) {
+    let config_path = versions_dir.join(get_config_file_name());
+    let config = match Config::from_path(&config_path) {
         Ok(config) => config,
-        Err(err) => panic!("{}", err),
+        Err(err) => panic!("{}", describe(&err)),
+    };
+    logging::set_level(Level::from_debug(config.debug));
+    let original_launcher_path = program_dir.join(ORIGINAL_LAUNCHER_FILE_NAME);
+    if started_by_steam() {
+        // Steam is already hosting us, so going through it again would only add a delay, or loop.
+        log_info!("Started by Steam. Launching Roblox directly.");
+        let arguments: Vec<LaunchArgument> = arguments.into_iter().map(LaunchArgument::new).collect();
+        return launch_original(&config, &original_launcher_path, &arguments);
+    }
+    let request = LaunchRequest::new(arguments);
+    let handoff = match HandoffServer::start(versions_dir, request) {
+        Ok(handoff) => handoff,
+        Err(err) => panic!("{}", describe(&err)),
     };
-    config.arguments = get_intended_arguments(&mut std::env::args());
-    match config.write_to_path(&config_path) {
-        Ok(_) => (),
-        Err(err) => panic!("{}", err),
+    log_info!("Queued launch request {} with {} arguments", handoff.request().id, handoff.request().arguments.len());
+    // The request is waiting for the master. Launch the master through Steam so we can start the game.
+    let steam_failure = match launch_steam(platform, &config.steam_launch, &config.steam_gameid) {
+        Err(err) => Some(format!("Failed to launch Steam: {}", describe(&err))),
+        Ok(_) => match handoff.wait_for_ack(Duration::from_secs(config.handoff_timeout_secs)) {
+            Ok(_) => {
+                log_info!("The master took launch request {}", handoff.request().id);
+                None
+            },
+            Err(HandoffError::TimedOut) => Some(format!("Steam did not start the master within {} seconds", config.handoff_timeout_secs)),
+            Err(err) => Some(describe(&err)),
+        },
     };
-    // arguments are written to config file. Launch the master exe so we can start the game.
-    match launch_steam(config.steam_gameid) {
-        Ok(_) => (),
-        Err(err) => panic!("Failed to launch steam: {}", err)
+    let reason = match steam_failure {
+        Some(reason) => reason,
+        None => return,
     };
+    // Steam never got the request to the master, so launch Roblox ourselves unless the master
+    // took it at the last moment or dropped it on purpose.
+    match handoff.withdraw() {
+        Ok(Withdrawal::Withdrawn) => (),
+        Ok(Withdrawal::Acknowledged) => {
+            log_info!("The master took launch request {} after all", handoff.request().id);
+            return;
+        },
+        Ok(Withdrawal::Discarded(reason)) => {
+            println!("The master dropped launch request {}: {}", handoff.request().id, reason);
+            log_warn!("The master dropped launch request {}: {}", handoff.request().id, reason);
+            return;
+        },
+        // The master may be about to connect, so give it the chance before launching ourselves.
+        Ok(Withdrawal::Claimed) => if handoff.wait_for_ack(HANDOFF_CONNECT_TIMEOUT).is_ok() {
+            log_info!("The master took launch request {} after all", handoff.request().id);
+            return;
+        },
+        Err(err) => panic!("{}", describe(&err)),
+    }
+    let record = FallbackRecord::new(&handoff.request().id, reason);
+    println!("{}. Launching Roblox without Steam.", record.reason);
+    log_warn!("{}. Launching Roblox without Steam.", record.reason);
+    if let Err(err) = record.write_to(versions_dir) {
+        println!("Could not record the failed launch: {}", err);
+        log_warn!("Could not record the failed launch: {}", describe(&err));
+    }
+    launch_original(&config, &original_launcher_path, &handoff.request().arguments);
+}
+
+/// Launches Roblox's own launcher with `arguments`, unless they hold something other than what
+/// Roblox's website launches it with and the config doesn't allow that.
+fn launch_original(config: &Config, original_launcher_path: &PathBuf, arguments: &[LaunchArgument]) {
+    if let Err(err) = check_arguments(arguments) {
+        if !config.allow_unrecognized_arguments {
+            println!("Refused to launch Roblox with unexpected arguments: {}", err);
+            log_error!("Refused to launch Roblox with unexpected arguments: {}", err);
+            return;
+        }
+        log_warn!("Launching anyway because allow_unrecognized_arguments is set: {}", err);
+    }
+    if let Err(err) = launch_game(original_launcher_path, arguments) {
+        panic!("Failed to launch Roblox: {}", describe(&err));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use roblox_steam_launcher_shared::platform::FakePlatform;
+    use std::fs;
+    use std::thread;
+
+    /// Makes a version directory whose launcher is a script recording what it was started with.
+    #[cfg(unix)]
+    fn test_directories(name: &str) -> (PathBuf, PathBuf) {
+        use std::os::unix::fs::PermissionsExt;
+
+        let versions_dir = std::env::temp_dir().join(format!("roblox_steam_launcher_substitute_test_{}_{}", std::process::id(), name));
+        let program_dir = versions_dir.join("version-abc");
+        fs::create_dir_all(&program_dir).unwrap();
+        let mut config = Config::new();
+        config.steam_gameid = String::from("123");
+        config.write_to_path(&versions_dir.join(get_config_file_name())).unwrap();
+        let launcher = program_dir.join(ORIGINAL_LAUNCHER_FILE_NAME);
+        fs::write(&launcher, "#!/bin/sh\necho \"$@\" > \"$(dirname \"$0\")/launched\"\n").unwrap();
+        fs::set_permissions(&launcher, fs::Permissions::from_mode(0o755)).unwrap();
+        (versions_dir, program_dir)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn launches_roblox_when_steam_is_unreachable() {
+        if started_by_steam() {
+            return;
+        }
+        let (versions_dir, program_dir) = test_directories("unreachable");
+        let mut platform = FakePlatform::new();
+        platform.rejects_urls = true;
+
+        let uri = "roblox-player:1+launchmode:play";
+        run(&platform, &program_dir, &versions_dir, vec![String::from(uri)]);
+        assert_eq!(platform.opened_urls(), vec![String::from("steam://rungameid/123")]);
+        assert!(FallbackRecord::take(&versions_dir).unwrap().unwrap().reason.starts_with("Failed to launch Steam"));
+        let launched = program_dir.join("launched");
+        for _ in 0..100 {
+            if let Ok(arguments) = fs::read_to_string(&launched) {
+                assert_eq!(arguments, format!("{}\n", uri));
+                fs::remove_dir_all(&versions_dir).unwrap();
+                return;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        panic!("Roblox was not launched");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_unexpected_arguments() {
+        if started_by_steam() {
+            return;
+        }
+        let (versions_dir, program_dir) = test_directories("refuses");
+        let mut platform = FakePlatform::new();
+        platform.rejects_urls = true;
+
+        run(&platform, &program_dir, &versions_dir, vec![String::from("roblox-player:1+launchmode:play"), String::from("--inject")]);
+        assert!(FallbackRecord::take(&versions_dir).unwrap().is_some());
+        // Nothing was spawned, so there is nothing to wait for.
+        assert!(!program_dir.join("launched").exists());
+        fs::remove_dir_all(&versions_dir).unwrap();
+    }
 }